@@ -15,13 +15,13 @@
 //! Operations on the off-chain testing environment.
 
 use super::{
-    chain_extension::ChainExtension,
     db::ExecContext,
     AccountError,
     EnvInstance,
     OnInstance,
 };
 pub use super::{
+    chain_extension::ChainExtension,
     db::ChainSpec,
     CallData,
     EmittedEvent,
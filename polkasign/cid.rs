@@ -0,0 +1,148 @@
+use ink_prelude::vec::Vec;
+
+/// Multihash code of sha2-256.
+pub const SHA2_256: u64 = 0x12;
+/// Multihash code of blake2b-256.
+pub const BLAKE2B_256: u64 = 0xb220;
+
+const DAG_PB: u64 = 0x70;
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE32_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cid {
+    pub version: u64,
+    pub codec: u64,
+    pub hash_code: u64,
+    pub digest: Vec<u8>,
+}
+
+/// Parse the cid from ipfs url, returns the cid and whether a sub path follows it.
+///
+/// Accepted forms: `<cid>`, `ipfs://<cid>`, `/ipfs/<cid>`, `http(s)://<host>/ipfs/<cid>`
+/// and subdomain gateway `http(s)://<cid>.ipfs.<host>`, `?query` and `#fragment` are ignored.
+pub fn parse_ipfs_url(url: &str) -> Option<(Cid, bool)> {
+    let url = match url.find(['?', '#']) {
+        Some(pos) => &url[..pos],
+        None => url,
+    };
+    let rest = if let Some(rest) = url.strip_prefix("ipfs://") {
+        rest
+    } else if let Some(rest) = url.strip_prefix("/ipfs/") {
+        rest
+    } else if let Some(location) = url.strip_prefix("http://").or_else(|| url.strip_prefix("https://")) {
+        let (host, path) = match location.find('/') {
+            Some(pos) => location.split_at(pos),
+            None => (location, ""),
+        };
+        if let Some(pos) = host.find(".ipfs.") {
+            return parse_cid(&host[..pos]).map(|cid| (cid, path.len() > 1));
+        }
+        let pos = path.find("/ipfs/")?;
+        &path[pos + "/ipfs/".len()..]
+    } else {
+        url
+    };
+    let (cid, sub_path) = match rest.find('/') {
+        Some(pos) => (&rest[..pos], rest.len() > pos + 1),
+        None => (rest, false),
+    };
+    parse_cid(cid).map(|cid| (cid, sub_path))
+}
+
+/// Parse CIDv0 (base58btc `Qm...`) or multibase encoded CIDv1.
+pub fn parse_cid(cid: &str) -> Option<Cid> {
+    if cid.len() == 46 && cid.starts_with("Qm") {
+        let bytes = decode_base58(cid.as_bytes())?;
+        let (hash_code, digest) = parse_multihash(&bytes)?;
+        return Some(Cid { version: 0, codec: DAG_PB, hash_code, digest });
+    }
+    let (base, data) = cid.as_bytes().split_first()?;
+    let bytes = match base {
+        b'b' => decode_base32(data)?,
+        b'B' => decode_base32(&data.to_ascii_lowercase())?,
+        b'z' => decode_base58(data)?,
+        b'f' => decode_base16(data)?,
+        _ => return None,
+    };
+    let (version, rest) = read_varint(&bytes)?;
+    if version != 1 {
+        return None;
+    }
+    let (codec, rest) = read_varint(rest)?;
+    let (hash_code, digest) = parse_multihash(rest)?;
+    Some(Cid { version, codec, hash_code, digest })
+}
+
+fn parse_multihash(bytes: &[u8]) -> Option<(u64, Vec<u8>)> {
+    let (code, rest) = read_varint(bytes)?;
+    let (len, digest) = read_varint(rest)?;
+    if digest.is_empty() || digest.len() as u64 != len {
+        return None;
+    }
+    Some((code, digest.to_vec()))
+}
+
+/// Unsigned LEB128 varint, at most 9 bytes.
+fn read_varint(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value: u64 = 0;
+    for (i, b) in bytes.iter().enumerate().take(9) {
+        value |= ((b & 0x7f) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((value, &bytes[i + 1..]));
+        }
+    }
+    None
+}
+
+fn decode_base58(data: &[u8]) -> Option<Vec<u8>> {
+    // big-endian base256 digits
+    let mut bytes: Vec<u8> = Vec::new();
+    for ch in data {
+        let mut carry = BASE58_ALPHABET.iter().position(|c| c == ch)? as u32;
+        for b in bytes.iter_mut().rev() {
+            carry += (*b as u32) * 58;
+            *b = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, (carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = data.iter().take_while(|c| **c == b'1').count();
+    let mut result = Vec::with_capacity(zeros + bytes.len());
+    result.resize(zeros, 0);
+    result.extend_from_slice(&bytes);
+    Some(result)
+}
+
+/// Rfc4648 lower case base32 without padding.
+fn decode_base32(data: &[u8]) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(data.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for ch in data {
+        let value = BASE32_ALPHABET.iter().position(|c| c == ch)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(result)
+}
+
+fn decode_base16(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+    let nibble = |ch: u8| match ch {
+        b'0'..=b'9' => Some(ch - b'0'),
+        b'a'..=b'f' => Some(ch - b'a' + 10),
+        _ => None,
+    };
+    data.chunks(2).map(|pair| Some(nibble(pair[0])? << 4 | nibble(pair[1])?)).collect()
+}
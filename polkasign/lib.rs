@@ -2,6 +2,9 @@
 
 extern crate alloc;
 use ink_lang as ink;
+
+mod merkle;
+mod cid;
 use ink_env::{Environment};
 use ink_prelude::vec::Vec;
 
@@ -54,8 +57,15 @@ impl Environment for CustomEnvironment {
     type ChainExtension = CryptoExtension;
 }
 
+pub use self::polkasign::{
+    AgreementRegistry, Polkasign, CreateAgreementParams, LifecycleStatus, DocumentVerifyInfo,
+    StorageInfo, StorageBackend, HashAlgorithm, SignInfo, AttestationInfo, AgreementLink, LinkType,
+    ClauseInfo, Beneficiary, MilestoneParams, CallbackInfo,
+};
+
 #[ink::contract(env = crate::CustomEnvironment)]
 mod polkasign {
+    use ink_lang as ink;
     use alloc::string::String;
     use ink_prelude::vec::Vec;
     use ink_prelude::collections::BTreeMap;
@@ -66,21 +76,63 @@ mod polkasign {
     use crate::CryptoExtensionErr;
 
     use page_helper::{PageParams, PageResult, cal_pages};
+    use crate::merkle::{merkle_root, merkle_proof, verify_merkle_proof};
+    use ink_env::hash::{Blake2x256, HashOutput, Keccak256, Sha2x256};
+    use crate::cid::{parse_ipfs_url, SHA2_256, BLAKE2B_256};
 
-    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    // agreement status
+    const STATUS_INIT: u8 = 0;
+    const STATUS_WAITING: u8 = 1;
+    const STATUS_FINISHED: u8 = 2;
+    // finished, then a linked agreement superseding or terminating it finished
+    const STATUS_SUPERSEDED: u8 = 3;
+    const STATUS_TERMINATED: u8 = 4;
+    // changes blocked until arbiter resolved
+    const STATUS_DISPUTED: u8 = 5;
+    const STATUS_CANCELLED: u8 = 6;
+
+    #[derive(Debug, scale::Encode, scale::Decode, Copy, Clone, Default, PartialEq, Eq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum StorageBackend {
+        Ipfs,
+        Arweave,
+        Crust,
+        Http,
+        #[default]
+        Other,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Copy, Clone, Default, PartialEq, Eq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum HashAlgorithm {
+        #[default]
+        Sha2x256,
+        Blake2x256,
+        Keccak256,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, Default, SpreadLayout, PackedLayout)]
     #[cfg_attr(
     feature = "std",
     derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
     )]
     pub struct StorageInfo {
-        hash: Hash,
-        creator: AccountId,
+        pub hash: Hash,
+        // how hash is computed from the file
+        pub hash_algo: HashAlgorithm,
+        pub creator: AccountId,
         // for what, like document comment
-        usage: String,
-        // save in what storage, like ipfs
-        save_at: String,
+        pub usage: String,
+        // save in what storage, ipfs url is validated against hash
+        pub save_at: StorageBackend,
         // resource address
-        url: String,
+        pub url: String,
     }
 
     #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
@@ -89,9 +141,252 @@ mod polkasign {
     derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
     )]
     pub struct SignInfo {
-        sign: Vec<u8>,
-        addr: AccountId,
-        create_at: u64,
+        pub sign: Vec<u8>,
+        pub addr: AccountId,
+        pub create_at: u64,
+        // notary attestation of this sign
+        pub attestation: Option<AttestationInfo>,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct AttestationInfo {
+        pub notary: AccountId,
+        // notary evidence, like identity check record
+        pub evidence: StorageInfo,
+        pub create_at: u64,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct ResourceInfo {
+        // position in agreement resources
+        id: u64,
+        attacher: AccountId,
+        attach_at: u64,
+        info: StorageInfo,
+        // tombstone if retracted by attacher
+        retraction: Option<RetractionInfo>,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct RetractionInfo {
+        by: AccountId,
+        retract_at: u64,
+        reason: String,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct MilestoneParams {
+        // erc20 token contract paying the milestone
+        pub token: AccountId,
+        pub payer: AccountId,
+        pub payee: AccountId,
+        pub amount: Balance,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct MilestoneInfo {
+        id: u32,
+        token: AccountId,
+        payer: AccountId,
+        payee: AccountId,
+        amount: Balance,
+        // signed by payee
+        delivered: Option<SignInfo>,
+        // signed by payer, token transferred from payer to payee
+        approved: Option<SignInfo>,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct CertificateToken {
+        token_id: u64,
+        owner: AccountId,
+        // agreement index
+        index: u64,
+        seal_hash: Hash,
+        mint_at: u64,
+        transferable: bool,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct CallbackInfo {
+        // contract notified when agreement finished or cancelled
+        pub callee: AccountId,
+        // message of callee taking (index: u64, seal_hash: Option<Hash>), seal_hash is none when cancelled
+        pub selector: [u8; 4],
+        // 0 would forward all remaining gas, so must be in 1..=MAX_CALLBACK_GAS
+        pub gas_limit: u64,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct CallbackResult {
+        // agreement status the callback notified
+        status: u8,
+        success: bool,
+        call_at: u64,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct ClauseInfo {
+        pub id: u32,
+        pub title: String,
+        pub content_hash: Hash,
+        // must be accepted by all signers before finished
+        pub mandatory: bool,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct ClauseDecision {
+        clause_id: u32,
+        accept: bool,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct ClauseResult {
+        clause: ClauseInfo,
+        accepted_by: Vec<AccountId>,
+        rejected_by: Vec<AccountId>,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Copy, Clone, PartialEq, Eq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum LinkType {
+        Addendum,
+        Renewal,
+        // parent is superseded once this agreement finished
+        Supersedes,
+        // parent is terminated once this agreement finished
+        Terminates,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct AgreementLink {
+        // parent agreement index
+        pub index: u64,
+        pub link_type: LinkType,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Copy, Clone, PartialEq, Eq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum LifecycleStatus {
+        // not finished, or before effective_from
+        Pending,
+        InEffect,
+        // terminates_at passed
+        Expired,
+        TerminatedEarly,
+        Superseded,
+        Disputed,
+        Cancelled,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Copy, Clone, PartialEq, Eq, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum DisputeOutcome {
+        // agreement stands, status restored
+        Upheld,
+        // agreement cancelled
+        Voided,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct DisputeInfo {
+        raiser: AccountId,
+        evidence: StorageInfo,
+        raise_at: u64,
+        // status before disputed
+        prev_status: u8,
+        outcome: Option<DisputeOutcome>,
+        ruling: Option<StorageInfo>,
+        resolve_at: u64,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Beneficiary {
+        pub account: AccountId,
+        // weight of escrow released to account
+        pub share: u32,
+    }
+
+    #[derive(Debug, Default, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct EscrowInfo {
+        // escrow held by contract
+        balance: Balance,
+        // map deposits: accountId -> value
+        deposits: BTreeMap<AccountId, Balance>,
+        beneficiaries: Vec<Beneficiary>,
+        // released to beneficiaries when agreement finished
+        released: bool,
+        // payouts failed on release, withdrawn by beneficiaries later
+        unpaid: BTreeMap<AccountId, Balance>,
     }
 
     #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
@@ -104,15 +399,65 @@ mod polkasign {
         creator: AccountId,
         name: String,
         create_at: u64,
-        // init=0, waiting=1, finished=2
+        // init=0, waiting=1, finished=2, superseded=3, terminated=4, disputed=5, cancelled=6
         status: u8,
         signers: Vec<AccountId>,
         agreement_file: StorageInfo,
+        // schedules and exhibits after agreement file
+        documents: Vec<StorageInfo>,
+        // merkle root of agreement file and documents hashes, which signers sign
+        sign_hash: Hash,
+        // every sign must be attested by a notary before finished
+        require_attestation: bool,
         // map signs: accountId -> sign
         sign_infos: BTreeMap<AccountId, SignInfo>,
-        // map resources: accountId -> resources vec
-        // like comment
-        resources: BTreeMap<AccountId, Vec<StorageInfo>>,
+        // resources in attachment order, like comment
+        resources: Vec<ResourceInfo>,
+        clauses: Vec<ClauseInfo>,
+        // map clause decisions: accountId -> (clause id -> accept)
+        clause_decisions: BTreeMap<AccountId, BTreeMap<u32, bool>>,
+        finish_at: u64,
+        // seal of the finished agreement, signs frozen after sealed
+        seal_hash: Option<Hash>,
+        // agreement file url is empty until revealed by creator
+        url_commitment: Option<Hash>,
+        tags: Vec<String>,
+        metadata: BTreeMap<String, String>,
+        parent: Option<AgreementLink>,
+        // linked agreements referencing this as parent
+        children: Vec<u64>,
+        effective_from: Option<u64>,
+        terminates_at: Option<u64>,
+        // time of mutual early termination
+        terminate_at: u64,
+        // map termination signs: accountId -> sign
+        termination_signs: BTreeMap<AccountId, SignInfo>,
+        arbiter: Option<AccountId>,
+        disputes: Vec<DisputeInfo>,
+        escrow: EscrowInfo,
+        milestones: Vec<MilestoneInfo>,
+        transferable_certificates: bool,
+        callback: Option<CallbackInfo>,
+        callback_results: Vec<CallbackResult>,
+        // storage deposit refunded to creator when archived
+        deposit: Balance,
+        // legal hold by owner, changes are blocked while frozen
+        frozen: bool,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct DocumentProof {
+        document: StorageInfo,
+        // position of document, agreement file is 0
+        doc_index: u32,
+        total: u32,
+        // sibling hashes from document up to root
+        proof: Vec<Hash>,
+        root: Hash,
     }
 
     #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
@@ -125,15 +470,45 @@ mod polkasign {
         creator: AccountId,
         name: String,
         create_at: u64,
-        // init=0, waiting=1, finished=2
+        // init=0, waiting=1, finished=2, superseded=3, terminated=4, disputed=5, cancelled=6
         status: u8,
         signers: Vec<AccountId>,
         agreement_file: StorageInfo,
+        // schedules and exhibits after agreement file
+        documents: Vec<StorageInfo>,
+        // merkle root of agreement file and documents hashes, which signers sign
+        sign_hash: Hash,
+        // every sign must be attested by a notary before finished
+        require_attestation: bool,
         // map signs: accountId -> sign
         sign_infos: Vec<SignInfo>,
-        // map resources: accountId -> resources vec
-        // like comment
+        // resources in attachment order, like comment
         resources: Vec<StorageInfo>,
+        clauses: Vec<ClauseResult>,
+        finish_at: u64,
+        seal_hash: Option<Hash>,
+        // agreement file url is empty until revealed by creator
+        url_commitment: Option<Hash>,
+        tags: Vec<String>,
+        metadata: BTreeMap<String, String>,
+        parent: Option<AgreementLink>,
+        // linked agreements referencing this as parent
+        children: Vec<u64>,
+        effective_from: Option<u64>,
+        terminates_at: Option<u64>,
+        terminate_at: u64,
+        termination_signs: Vec<SignInfo>,
+        arbiter: Option<AccountId>,
+        disputes: Vec<DisputeInfo>,
+        escrow: EscrowInfo,
+        milestones: Vec<MilestoneInfo>,
+        transferable_certificates: bool,
+        callback: Option<CallbackInfo>,
+        callback_results: Vec<CallbackResult>,
+        // storage deposit refunded to creator when archived
+        deposit: Balance,
+        // legal hold by owner, changes are blocked while frozen
+        frozen: bool,
     }
 
     #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
@@ -141,10 +516,124 @@ mod polkasign {
     feature = "std",
     derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
     )]
-    pub struct CreateAgreementParams {
+    pub struct AgreementCertificate {
+        index: u64,
+        creator: AccountId,
         name: String,
+        create_at: u64,
+        finish_at: u64,
+        sign_hash: Hash,
         signers: Vec<AccountId>,
-        agreement_file: StorageInfo,
+        sign_infos: Vec<SignInfo>,
+        seal_hash: Hash,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, Default, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct CreateAgreementParams {
+        pub name: String,
+        pub signers: Vec<AccountId>,
+        pub agreement_file: StorageInfo,
+        // schedules and exhibits after agreement file
+        pub documents: Vec<StorageInfo>,
+        pub require_attestation: bool,
+        // optional clauses accepted individually by signers
+        pub clauses: Vec<ClauseInfo>,
+        // hide agreement file url behind blake2x256(url ++ salt) until finished
+        pub url_commitment: Option<Hash>,
+        // classification like nda, employment, vendor
+        pub tags: Vec<String>,
+        // metadata like internal reference number
+        pub metadata: BTreeMap<String, String>,
+        // link to parent agreement, like addendum or renewal
+        pub parent: Option<AgreementLink>,
+        // term of agreement, in block timestamp
+        pub effective_from: Option<u64>,
+        pub terminates_at: Option<u64>,
+        // resolves disputes raised by parties
+        pub arbiter: Option<AccountId>,
+        // receive escrow when agreement finished
+        pub beneficiaries: Vec<Beneficiary>,
+        // paid in erc20 token once agreement finished
+        pub milestones: Vec<MilestoneParams>,
+        // certificates minted to creator and signers are non-transferable by default
+        pub transferable_certificates: bool,
+        // notified when agreement finished or cancelled
+        pub callback: Option<CallbackInfo>,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct NotarizationInfo {
+        hash: Hash,
+        submitter: AccountId,
+        // first seen time
+        create_at: u64,
+        info: StorageInfo,
+        cosigns: Vec<SignInfo>,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct DocumentVerifyInfo {
+        pub index: u64,
+        pub name: String,
+        pub creator: AccountId,
+        pub status: u8,
+        pub signers: Vec<AccountId>,
+        // signs with signing time
+        pub sign_infos: Vec<SignInfo>,
+        pub seal_hash: Option<Hash>,
+        pub lifecycle: LifecycleStatus,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct CommentInfo {
+        // comment id in agreement
+        id: u64,
+        author: AccountId,
+        reply_to: Option<u64>,
+        content: StorageInfo,
+        create_at: u64,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct KeyEnvelope {
+        // encryption scheme id, decided by clients
+        scheme: u32,
+        // document key encrypted to the participant
+        ciphertext: Vec<u8>,
+        // increased on every rotation
+        key_version: u32,
+        update_at: u64,
+    }
+
+    #[derive(Debug, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout)]
+    #[cfg_attr(
+    feature = "std",
+    derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct KeyEnvelopeParams {
+        participant: AccountId,
+        scheme: u32,
+        ciphertext: Vec<u8>,
     }
 
     #[ink(event)]
@@ -160,6 +649,94 @@ mod polkasign {
         creator: AccountId,
     }
 
+    #[ink(event)]
+    pub struct CommentEvent {
+        #[ink(topic)]
+        index: u64,
+        id: u64,
+        author: AccountId,
+        reply_to: Option<u64>,
+    }
+
+    #[ink(event)]
+    pub struct DisputeRaisedEvent {
+        index: u64,
+        raiser: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct DisputeResolvedEvent {
+        index: u64,
+        arbiter: AccountId,
+        outcome: DisputeOutcome,
+    }
+
+    #[ink(event)]
+    pub struct CertificateTransferEvent {
+        #[ink(topic)]
+        token_id: u64,
+        from: Option<AccountId>,
+        to: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferEvent {
+        from: AccountId,
+        to: AccountId,
+        // false when started by owner, true when accepted by new owner
+        accepted: bool,
+    }
+
+    #[ink(event)]
+    pub struct PauseEvent {
+        paused: bool,
+    }
+
+    #[ink(event)]
+    pub struct FreezeEvent {
+        index: u64,
+        frozen: bool,
+    }
+
+    #[ink(event)]
+    pub struct WithdrawEvent {
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct NotarizeEvent {
+        hash: Hash,
+        submitter: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct AttestSignatureEvent {
+        index: u64,
+        signer: AccountId,
+        notary: AccountId,
+    }
+
+    /// Typed interface for other contracts to create, sign and check agreements.
+    #[ink::trait_definition]
+    pub trait AgreementRegistry {
+        /// Create agreement, returns its index.
+        #[ink(message)]
+        fn create(&mut self, params: CreateAgreementParams) -> u64;
+
+        /// Sign agreement with sign over its sign hash.
+        #[ink(message)]
+        fn sign(&mut self, index: u64, sign: [u8; 64]);
+
+        /// Lifecycle status of agreement.
+        #[ink(message)]
+        fn status(&self, index: u64) -> LifecycleStatus;
+
+        /// Find every agreement referencing the document.
+        #[ink(message)]
+        fn verify_document(&self, hash: Hash) -> Vec<DocumentVerifyInfo>;
+    }
+
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
     /// to add new static storage fields to your contract.
@@ -170,6 +747,35 @@ mod polkasign {
         agreements_map: StorageHashMap<u64, AgreementInfo>,
         agreements_creator_map: StorageHashMap<AccountId, Vec<u64>>,
         agreements_collaborator_map: StorageHashMap<AccountId, Vec<u64>>,
+        // map document hash -> agreements referencing it, by agreement file, documents or resources
+        agreements_hash_map: StorageHashMap<Hash, Vec<u64>>,
+        // map agreement -> comments in posting order
+        agreements_comment_map: StorageHashMap<u64, Vec<CommentInfo>>,
+        // map agreement -> (participant -> encrypted document key)
+        agreements_envelope_map: StorageHashMap<u64, BTreeMap<AccountId, KeyEnvelope>>,
+        // registered notaries: accountId -> register time
+        notaries: StorageHashMap<AccountId, u64>,
+        notarizations_map: StorageHashMap<Hash, NotarizationInfo>,
+        certificate_index: u64,
+        certificates_map: StorageHashMap<u64, CertificateToken>,
+        // map owner -> certificate token ids
+        certificates_owner_map: StorageHashMap<AccountId, Vec<u64>>,
+        // fee charged on create: create_fee + signer_fee * signers
+        create_fee: Balance,
+        signer_fee: Balance,
+        // accounts exempt from fee: accountId -> add time
+        fee_exempts: StorageHashMap<AccountId, u64>,
+        // collected fees, escrow and storage deposits are not included
+        treasury: Balance,
+        // value overpaid on create, withdrawn by payer: accountId -> value
+        refunds: StorageHashMap<AccountId, Balance>,
+        deposit_per_byte: Balance,
+        // map archived agreement -> seal hash
+        archived_map: StorageHashMap<u64, Option<Hash>>,
+        // set by owner, accepted by pending owner
+        pending_owner: Option<AccountId>,
+        // blocks state-changing messages except owner administration
+        paused: bool,
     }
 
     impl Polkasign {
@@ -180,14 +786,66 @@ mod polkasign {
                 index: 0,
                 agreements_map: StorageHashMap::new(),
                 agreements_creator_map: StorageHashMap::new(),
-                agreements_collaborator_map: StorageHashMap::new()
+                agreements_collaborator_map: StorageHashMap::new(),
+                agreements_hash_map: StorageHashMap::new(),
+                agreements_comment_map: StorageHashMap::new(),
+                agreements_envelope_map: StorageHashMap::new(),
+                notaries: StorageHashMap::new(),
+                notarizations_map: StorageHashMap::new(),
+                certificate_index: 0,
+                certificates_map: StorageHashMap::new(),
+                certificates_owner_map: StorageHashMap::new(),
+                create_fee: 0,
+                signer_fee: 0,
+                fee_exempts: StorageHashMap::new(),
+                treasury: 0,
+                refunds: StorageHashMap::new(),
+                deposit_per_byte: 0,
+                archived_map: StorageHashMap::new(),
+                pending_owner: None,
+                paused: false,
             }
         }
 
-        #[ink(message)]
+        // storage deposit is sized at creation, so growth after creation is capped
+        const MAX_CHILDREN: usize = 16;
+        const MAX_RESOURCES: usize = 64;
+        const MAX_COMMENTS: usize = 256;
+        const MAX_DISPUTES: usize = 8;
+        // callback can't exhaust gas of the sign, cancel or ruling notifying it
+        const MAX_CALLBACK_GAS: u64 = 10_000_000_000;
+
+        #[ink(message, payable)]
         pub fn create_agreement(&mut self, params: CreateAgreementParams) -> u64 {
+            self._assert_not_paused();
             let caller = self.env().caller();
             assert!(self.index + 1 > self.index, "index overflow");
+            Polkasign::_validate_labels(&params.tags, &params.metadata);
+            if let (Some(from), Some(to)) = (params.effective_from, params.terminates_at) {
+                assert!(from < to, "wrong term");
+            }
+            Polkasign::_validate_beneficiaries(&params.beneficiaries);
+            if let Some(link) = &params.parent {
+                let parent = self.agreements_map.get(&link.index).expect("parent not found");
+                assert!(Polkasign::_is_party(parent, &caller), "not party of parent");
+                Polkasign::_assert_mutable(parent);
+                assert!(parent.children.len() < Polkasign::MAX_CHILDREN, "too many children");
+                // closing parent needs consent of all its signers
+                if link.link_type == LinkType::Supersedes || link.link_type == LinkType::Terminates {
+                    assert!(parent.signers.iter().all(|s| params.signers.contains(s)), "parent signers missing");
+                }
+            }
+            for (i, clause) in params.clauses.iter().enumerate() {
+                assert!(params.clauses[..i].iter().all(|c| c.id != clause.id), "duplicate clause id");
+            }
+            for m in params.milestones.iter() {
+                assert!(params.signers.contains(&m.payer) && params.signers.contains(&m.payee), "not found in signers");
+                assert!(m.payer != m.payee, "payer is payee");
+                assert!(m.amount > 0, "zero amount");
+            }
+            if let Some(callback) = &params.callback {
+                assert!(callback.gas_limit > 0 && callback.gas_limit <= Polkasign::MAX_CALLBACK_GAS, "wrong callback gas limit");
+            }
             let index = self.index;
             self.index += 1;
 
@@ -201,18 +859,73 @@ mod polkasign {
 
             let mut storage_info = params.agreement_file;
             storage_info.creator = caller;
-            let info = AgreementInfo{
-                index,
+            match params.url_commitment {
+                Some(_) => assert!(storage_info.url.is_empty(), "url not hidden"),
+                None => Polkasign::_validate_storage_info(&storage_info),
+            }
+            let mut documents = params.documents;
+            for doc in documents.iter_mut() {
+                doc.creator = caller;
+                Polkasign::_validate_storage_info(doc);
+            }
+            let sign_hash = Polkasign::_document_root(&storage_info, &documents);
+            self._index_document(storage_info.hash, index);
+            for doc in documents.iter() {
+                self._index_document(doc.hash, index);
+            }
+            let mut info = AgreementInfo{
+                index,
                 creator: caller,
                 name: params.name.clone(),
                 create_at: self.env().block_timestamp(),
-                status: 0,
+                status: STATUS_INIT,
                 signers: params.signers,
                 agreement_file: storage_info,
+                documents,
+                sign_hash,
+                require_attestation: params.require_attestation,
                 sign_infos: BTreeMap::new(),
-                resources: BTreeMap::new(),
+                resources: Vec::new(),
+                clauses: params.clauses,
+                clause_decisions: BTreeMap::new(),
+                finish_at: 0,
+                seal_hash: None,
+                url_commitment: params.url_commitment,
+                tags: params.tags,
+                metadata: params.metadata,
+                parent: params.parent.clone(),
+                children: Vec::new(),
+                effective_from: params.effective_from,
+                terminates_at: params.terminates_at,
+                terminate_at: 0,
+                termination_signs: BTreeMap::new(),
+                arbiter: params.arbiter,
+                disputes: Vec::new(),
+                escrow: EscrowInfo {
+                    beneficiaries: params.beneficiaries,
+                    ..Default::default()
+                },
+                milestones: params.milestones.into_iter().enumerate().map(|(i, m)| MilestoneInfo {
+                    id: i as u32,
+                    token: m.token,
+                    payer: m.payer,
+                    payee: m.payee,
+                    amount: m.amount,
+                    delivered: None,
+                    approved: None,
+                }).collect(),
+                transferable_certificates: params.transferable_certificates,
+                callback: params.callback,
+                callback_results: Vec::new(),
+                deposit: 0,
+                frozen: false,
             };
+            info.deposit = self.deposit_per_byte.checked_mul(scale::Encode::encoded_size(&info) as Balance).expect("deposit overflow");
+            self._charge_fee(caller, info.signers.len(), info.deposit);
             self.agreements_map.insert(index, info);
+            if let Some(link) = params.parent {
+                self.agreements_map.get_mut(&link.index).unwrap().children.push(index);
+            }
             self.env().emit_event(CreateAgreementEvent {
                 index,
                 creator: caller,
@@ -221,347 +934,3291 @@ mod polkasign {
             index
         }
 
-        #[ink(message)]
+        #[ink(message, payable)]
         pub fn create_agreement_with_sign(&mut self, params: CreateAgreementParams, info: StorageInfo, sign: [u8; 64]) {
             let caller = self.env().caller();
             let time_at = self.env().block_timestamp();
             let index = self.create_agreement(params);
             let a = self.agreements_map.get(&index).unwrap();
 
-            let file_hash = a.agreement_file.hash.clone();
-            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *file_hash.as_ref(), sign.clone()), "wrong sign");
-            // if sign enough, set waiting
-            let a = self.agreements_map.get_mut(&index).unwrap();
-            a.status = 1;
-            a.sign_infos.insert(caller, SignInfo{
-                sign: sign.to_vec(),
-                addr: caller,
-                create_at: time_at,
-            });
-            // if sign enough, set finished
-            if a.sign_infos.len() >= a.signers.len() {
-                a.status = 2;
-            }
+            let sign_hash = Polkasign::_clause_sign_hash(a, &[]);
+            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *sign_hash.as_ref(), sign), "wrong sign");
+            self._set_clause_decisions(index, caller, Vec::new());
+            self._add_sign(index, caller, sign, time_at);
 
-            let resources = a.resources.entry(caller.clone()).or_insert(Vec::new());
-            resources.push(info);
+            self._push_resource(index, caller, info);
         }
 
         #[ink(message)]
         pub fn attach_resource_to_agreement(&mut self, index: u64, info: StorageInfo) {
+            self._assert_not_paused();
             let caller = self.env().caller();
-            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            let agreement = self.agreements_map.get(&index).unwrap();
             assert!(agreement.signers.contains(&caller), "not found in signers");
+            Polkasign::_assert_mutable(agreement);
 
-            let storage_hash = info.hash;
-            let resources = agreement.resources.entry(caller.clone()).or_insert(Vec::new());
-            resources.push(info);
+            self._push_resource(index, caller, info);
             self.env().emit_event(UpdateAgreementEvent {
                 index,
                 creator: caller,
             });
         }
 
+        fn _push_resource(&mut self, index: u64, attacher: AccountId, info: StorageInfo) {
+            Polkasign::_validate_storage_info(&info);
+            let time_at = self.env().block_timestamp();
+            let storage_hash = info.hash;
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            assert!(agreement.resources.len() < Polkasign::MAX_RESOURCES, "too many resources");
+            let id = agreement.resources.len() as u64;
+            agreement.resources.push(ResourceInfo {
+                id,
+                attacher,
+                attach_at: time_at,
+                info,
+                retraction: None,
+            });
+            self._index_document(storage_hash, index);
+        }
+
+        /// Retract own resource before the agreement finished, a tombstone is left.
         #[ink(message)]
-        pub fn attach_resource_with_sign(&mut self, index: u64, info: StorageInfo, sign: [u8; 64]) {
+        pub fn retract_resource(&mut self, index: u64, resource_id: u64, reason: String) {
+            self._assert_not_paused();
             let caller = self.env().caller();
             let time_at = self.env().block_timestamp();
-            self.attach_resource_to_agreement(index, info);
-            let agreement = self.agreements_map.get(&index).unwrap();
-            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *agreement.agreement_file.hash.as_ref(), sign.clone()), "wrong sign");
-
             let agreement = self.agreements_map.get_mut(&index).unwrap();
-            agreement.sign_infos.insert(caller, SignInfo{
-                sign: sign.to_vec(),
-                addr: caller,
-                create_at: time_at,
+            assert!(Polkasign::_is_open(agreement), "agreement closed");
+            Polkasign::_assert_mutable(agreement);
+            let resource = agreement.resources.get_mut(resource_id as usize).expect("resource not found");
+            assert!(resource.attacher == caller, "not attacher");
+            assert!(resource.retraction.is_none(), "already retracted");
+            assert!(reason.len() <= Polkasign::MAX_REASON_LEN, "reason too long");
+            resource.retraction = Some(RetractionInfo {
+                by: caller,
+                retract_at: time_at,
+                reason,
+            });
+            let storage_hash = resource.info.hash;
+            if !Polkasign::_references_document(agreement, &storage_hash) {
+                self._unindex_document(storage_hash, index);
+            }
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: caller,
             });
-            agreement.status = 1;
+        }
 
-            // if sign enough, set finished
-            if agreement.sign_infos.len() >= agreement.signers.len() {
-                agreement.status = 2;
+        /// Query resources in attachment order, optionally only those attached by account.
+        #[ink(message)]
+        pub fn query_resources(&self, index: u64, account: Option<AccountId>, include_retracted: bool, page_params: PageParams) -> PageResult<ResourceInfo> {
+            let agreement = self.agreements_map.get(&index).unwrap();
+            let list: Vec<&ResourceInfo> = agreement.resources.iter()
+                .filter(|r| account.is_none_or(|a| r.attacher == a))
+                .filter(|r| include_retracted || r.retraction.is_none())
+                .collect();
+            let total = list.len() as u64;
+            let (start, end, pages) = cal_pages(&page_params, total);
+            let mut result = Vec::new();
+            for i in start..end {
+                result.push(list[i as usize].clone());
+            }
+            PageResult {
+                success: true,
+                err: String::from("success"),
+                total,
+                pages,
+                page_index: page_params.page_index,
+                page_size: page_params.page_size,
+                data: result,
             }
         }
 
         #[ink(message)]
-        pub fn check_sr25519_sign(&mut self, msg: [u8; 32], sign: [u8; 64]) -> bool {
+        pub fn attach_resource_with_sign(&mut self, index: u64, info: StorageInfo, sign: [u8; 64]) {
             let caller = self.env().caller();
-            assert!(self._check_sr25519_sign(*caller.as_ref(), msg, sign), "wrong sign");
-            true
+            let time_at = self.env().block_timestamp();
+            self.attach_resource_to_agreement(index, info);
+            let agreement = self.agreements_map.get(&index).unwrap();
+            let sign_hash = Polkasign::_clause_sign_hash(agreement, &[]);
+            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *sign_hash.as_ref(), sign), "wrong sign");
+            self._set_clause_decisions(index, caller, Vec::new());
+            self._add_sign(index, caller, sign, time_at);
         }
 
+        /// Sign the agreement, accepting or rejecting its clauses.
+        /// The sign covers `clause_sign_hash(index, decisions)`, signing again replaces decisions until finished.
         #[ink(message)]
-        pub fn check_sr25519_bytes_sign(&mut self, msg: [u8; 32], sign: [u8; 64]) -> bool {
+        pub fn sign_with_clauses(&mut self, index: u64, decisions: Vec<ClauseDecision>, sign: [u8; 64]) {
+            self._assert_not_paused();
             let caller = self.env().caller();
-            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), msg, sign), "wrong sign");
-            true
+            let time_at = self.env().block_timestamp();
+            let agreement = self.agreements_map.get(&index).unwrap();
+            assert!(agreement.signers.contains(&caller), "not found in signers");
+            assert!(Polkasign::_is_open(agreement), "agreement closed");
+            let sign_hash = Polkasign::_clause_sign_hash(agreement, &decisions);
+            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *sign_hash.as_ref(), sign), "wrong sign");
+            self._set_clause_decisions(index, caller, decisions);
+            self._add_sign(index, caller, sign, time_at);
         }
 
-        pub fn _check_sr25519_sign(&self, public: [u8; 32], msg: [u8; 32], sign: [u8; 64]) -> bool {
-            let res = self.env().extension().verify_sr25519(public, msg, sign);
-            if res.is_ok() {
-                return true;
-            }
-
-            return false
+        /// Hash a signer signs with these clause decisions.
+        /// It's the sign_hash if the agreement has no clauses and no decisions are made.
+        #[ink(message)]
+        pub fn clause_sign_hash(&self, index: u64, decisions: Vec<ClauseDecision>) -> Hash {
+            let agreement = self.agreements_map.get(&index).unwrap();
+            Polkasign::_clause_sign_hash(agreement, &decisions)
         }
 
-        const bytes_pre: [char; 7] = ['<', 'B', 'y', 't', 'e', 's', '>'];
-        const bytes_sub: [char; 8] = ['<', '/', 'B', 'y', 't', 'e', 's', '>'];
-        pub fn _check_sr25519_bytes_sign(&self, public: [u8; 32], msg: [u8; 32], sign: [u8; 64]) -> bool {
-            let mut tmp = [0; 47];
-            let mut index = 0;
-            for ch in Polkasign::bytes_pre {
-                tmp[index.clone()] = ch as u8;
-                index += 1;
-            }
-            for ch in msg {
-                tmp[index.clone()] = ch;
-                index += 1;
-            }
-            for ch in Polkasign::bytes_sub {
-                tmp[index.clone()] = ch as u8;
-                index += 1;
+        fn _clause_sign_hash(a: &AgreementInfo, decisions: &[ClauseDecision]) -> Hash {
+            if a.clauses.is_empty() && decisions.is_empty() {
+                return a.sign_hash;
             }
-            let res = self.env().extension().verify_sr25519_bytes(public, tmp, sign);
-            if res.is_ok() {
-                return true;
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(&(b"clauses", a.sign_hash, &a.clauses, decisions), &mut output);
+            output.into()
+        }
+
+        /// Replace the clause decisions of signer.
+        fn _set_clause_decisions(&mut self, index: u64, signer: AccountId, decisions: Vec<ClauseDecision>) {
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            let mut signer_decisions = BTreeMap::new();
+            for decision in decisions {
+                assert!(agreement.clauses.iter().any(|c| c.id == decision.clause_id), "clause not found");
+                signer_decisions.insert(decision.clause_id, decision.accept);
             }
+            agreement.clause_decisions.insert(signer, signer_decisions);
+        }
 
-            return false
+        /// Record a verified sign, and finish the agreement if the completion policy is met.
+        fn _add_sign(&mut self, index: u64, signer: AccountId, sign: [u8; 64], time_at: u64) {
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            assert!(agreement.signers.contains(&signer), "not found in signers");
+            assert!(Polkasign::_is_open(agreement), "agreement closed");
+            Polkasign::_assert_mutable(agreement);
+            agreement.sign_infos.insert(signer, SignInfo{
+                sign: sign.to_vec(),
+                addr: signer,
+                create_at: time_at,
+                attestation: None,
+            });
+            agreement.status = STATUS_WAITING;
+            self._try_finish(index);
         }
 
-        #[ink(message)]
-        pub fn query_agreement_by_creator(&mut self, creator: AccountId, pageParams: PageParams) -> PageResult<AgreementInfoDisplay> {
-            let list_res = self.agreements_creator_map.get(&creator);
-            if list_res.is_none() {
-                return PageResult{
-                    success: true,
-                    err: String::from("success"),
-                    total: 0,
-                    pages: 0,
-                    page_index: 0,
-                    page_size: 0,
-                    data: Vec::new(),
+        /// Set finished when all signers signed, all signs attested if required,
+        /// and all mandatory clauses accepted by all signers.
+        fn _try_finish(&mut self, index: u64) {
+            let time_at = self.env().block_timestamp();
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            if !Polkasign::_is_open(agreement) {
+                return;
+            }
+            for signer in agreement.signers.iter() {
+                match agreement.sign_infos.get(signer) {
+                    None => return,
+                    Some(sign_info) => {
+                        if agreement.require_attestation && sign_info.attestation.is_none() {
+                            return;
+                        }
+                    }
+                }
+                for clause in agreement.clauses.iter().filter(|c| c.mandatory) {
+                    let accepted = agreement.clause_decisions.get(signer)
+                        .and_then(|d| d.get(&clause.id))
+                        .cloned()
+                        .unwrap_or(false);
+                    if !accepted {
+                        return;
+                    }
                 }
             }
-            let ids = list_res.unwrap();
-            let total = ids.len() as u64;
-            let (start, end, pages) = cal_pages(&pageParams, total);
-            let mut result = Vec::new();
-            for i in start..end {
-                result.push(Polkasign::convAgreement2Display(self.agreements_map.get(&ids[i as usize]).unwrap()));
+            agreement.status = STATUS_FINISHED;
+            agreement.finish_at = time_at;
+            agreement.seal_hash = Some(Polkasign::_seal_hash(agreement));
+            self._update_parent_status(index);
+            self._release_escrow(index);
+            self._mint_certificates(index);
+            self._notify_callback(index);
+        }
+
+        /// Supersede or terminate the finished parent of a finished agreement.
+        fn _update_parent_status(&mut self, index: u64) {
+            let agreement = self.agreements_map.get(&index).unwrap();
+            if let Some(link) = &agreement.parent {
+                let parent_index = link.index;
+                self._apply_child_links(parent_index);
             }
-            return PageResult{
-                success: true,
-                err: String::from("success"),
-                total,
-                pages,
-                page_index: pageParams.page_index,
-                page_size: pageParams.page_size,
-                data: result,
+        }
+
+        /// Supersede or terminate the finished agreement by its first finished child linked so.
+        /// Deferred while the agreement is frozen or disputed, applied again once unfrozen or upheld.
+        fn _apply_child_links(&mut self, index: u64) {
+            let agreement = match self.agreements_map.get(&index) {
+                Some(agreement) => agreement,
+                // archived
+                None => return,
+            };
+            if agreement.status != STATUS_FINISHED || agreement.frozen {
+                return;
+            }
+            let linked = agreement.children.iter()
+                .filter_map(|id| self.agreements_map.get(id))
+                .filter(|child| child.seal_hash.is_some() && child.status != STATUS_CANCELLED)
+                .find_map(|child| match child.parent.as_ref().map(|link| link.link_type) {
+                    Some(LinkType::Supersedes) => Some((STATUS_SUPERSEDED, child.creator)),
+                    Some(LinkType::Terminates) => Some((STATUS_TERMINATED, child.creator)),
+                    _ => None,
+                });
+            if let Some((status, creator)) = linked {
+                self.agreements_map.get_mut(&index).unwrap().status = status;
+                self.env().emit_event(UpdateAgreementEvent {
+                    index,
+                    creator,
+                });
             }
         }
 
         #[ink(message)]
-        pub fn query_agreement_by_id(&mut self, index: u64) -> AgreementInfoDisplay {
+        pub fn query_lifecycle_status(&self, index: u64) -> LifecycleStatus {
             let a = self.agreements_map.get(&index).unwrap();
-            Polkasign::convAgreement2Display(a)
+            Polkasign::_lifecycle_status(a, self.env().block_timestamp())
+        }
+
+        fn _lifecycle_status(a: &AgreementInfo, now: u64) -> LifecycleStatus {
+            match a.status {
+                STATUS_TERMINATED => LifecycleStatus::TerminatedEarly,
+                STATUS_SUPERSEDED => LifecycleStatus::Superseded,
+                STATUS_DISPUTED => LifecycleStatus::Disputed,
+                STATUS_CANCELLED => LifecycleStatus::Cancelled,
+                STATUS_FINISHED => {
+                    if a.terminates_at.is_some_and(|t| now >= t) {
+                        LifecycleStatus::Expired
+                    } else if a.effective_from.is_some_and(|f| now < f) {
+                        LifecycleStatus::Pending
+                    } else {
+                        LifecycleStatus::InEffect
+                    }
+                }
+                _ => LifecycleStatus::Pending,
+            }
         }
 
+        /// The hash signers sign to terminate a finished agreement early.
         #[ink(message)]
-        pub fn owner(&self) -> AccountId {
-            self.owner
+        pub fn termination_hash(&self, index: u64) -> Hash {
+            let a = self.agreements_map.get(&index).unwrap();
+            let seal_hash = a.seal_hash.expect("agreement not finished");
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(&(b"terminate", seal_hash), &mut output);
+            output.into()
         }
 
+        /// Sign mutual early termination, terminated once all signers signed.
         #[ink(message)]
-        pub fn index(&self) -> u64 {
-            self.index.clone()
+        pub fn terminate_early(&mut self, index: u64, sign: [u8; 64]) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let time_at = self.env().block_timestamp();
+            let msg = self.termination_hash(index);
+            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *msg.as_ref(), sign), "wrong sign");
+            self._add_termination_sign(index, caller, sign, time_at);
         }
 
-        fn convAgreement2Display(a: &AgreementInfo) -> AgreementInfoDisplay {
-            let sign_infos = a.sign_infos.values().cloned().collect();
-            let mut resources: Vec<StorageInfo> = Vec::new();
-            let res: Vec<Vec<StorageInfo>> = a.resources.values().cloned().collect();
-            for item in res {
-                for info in item {
-                    resources.push(info);
-                }
+        fn _add_termination_sign(&mut self, index: u64, signer: AccountId, sign: [u8; 64], time_at: u64) {
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            assert!(agreement.signers.contains(&signer), "not found in signers");
+            assert!(agreement.status == STATUS_FINISHED, "agreement not finished");
+            Polkasign::_assert_mutable(agreement);
+            assert!(Polkasign::_lifecycle_status(agreement, time_at) != LifecycleStatus::Expired, "agreement expired");
+            agreement.termination_signs.insert(signer, SignInfo {
+                sign: sign.to_vec(),
+                addr: signer,
+                create_at: time_at,
+                attestation: None,
+            });
+            if agreement.signers.iter().all(|s| agreement.termination_signs.contains_key(s)) {
+                agreement.status = STATUS_TERMINATED;
+                agreement.terminate_at = time_at;
             }
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: signer,
+            });
+        }
 
-            AgreementInfoDisplay {
+        /// Whether agreement still accepts signs.
+        fn _is_open(a: &AgreementInfo) -> bool {
+            a.status == STATUS_INIT || a.status == STATUS_WAITING
+        }
+
+        /// Seal over sign hash, signer set, all signs and timestamps.
+        fn _seal_hash(a: &AgreementInfo) -> Hash {
+            // full sign infos, notary attestations included
+            let signs: Vec<&SignInfo> = a.sign_infos.values().collect();
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(&(a.index, a.sign_hash, &a.signers, signs, &a.clause_decisions, a.create_at, a.finish_at), &mut output);
+            output.into()
+        }
+
+        #[ink(message)]
+        pub fn get_certificate(&self, index: u64) -> AgreementCertificate {
+            let a = self.agreements_map.get(&index).unwrap();
+            let seal_hash = a.seal_hash.expect("agreement not finished");
+            AgreementCertificate {
                 index: a.index,
                 creator: a.creator,
                 name: a.name.clone(),
                 create_at: a.create_at,
-                status: a.status,
+                finish_at: a.finish_at,
+                sign_hash: a.sign_hash,
                 signers: a.signers.clone(),
-                agreement_file: a.agreement_file.clone(),
-                sign_infos,
-                resources
+                sign_infos: a.sign_infos.values().cloned().collect(),
+                seal_hash,
+            }
+        }
+
+        /// Collect fee into treasury and record value over deposit and fee as refund of caller,
+        /// must cover deposit and fee unless caller is exempt from fee.
+        fn _charge_fee(&mut self, caller: AccountId, signers: usize, deposit: Balance) {
+            let value = self.env().transferred_balance();
+            let fee = if self.fee_exempts.contains_key(&caller) {
+                0
+            } else {
+                self.signer_fee.checked_mul(signers as Balance)
+                    .and_then(|fee| fee.checked_add(self.create_fee))
+                    .expect("fee overflow")
+            };
+            assert!(value >= fee.checked_add(deposit).expect("fee overflow"), "insufficient fee");
+            self.treasury += fee;
+            let excess = value - fee - deposit;
+            if excess > 0 {
+                match self.refunds.get_mut(&caller) {
+                    Some(refund) => *refund += excess,
+                    None => {
+                        self.refunds.insert(caller, excess);
+                    }
+                }
             }
         }
 
         #[ink(message)]
-        pub fn query_agreement_by_collaborator(&mut self, collaborator: AccountId, pageParams: PageParams) -> PageResult<AgreementInfoDisplay> {
-            let list_res = self.agreements_collaborator_map.get(&collaborator);
-            if list_res.is_none() {
-                return PageResult{
-                    success: true,
-                    err: String::from("success"),
-                    total: 0,
-                    pages: 0,
-                    page_index: 0,
-                    page_size: 0,
-                    data: Vec::new(),
+        pub fn set_deposit_per_byte(&mut self, deposit_per_byte: Balance) {
+            self._assert_owner();
+            self.deposit_per_byte = deposit_per_byte;
+        }
+
+        /// Query storage deposit charged per encoded byte of created agreement.
+        #[ink(message)]
+        pub fn deposit_per_byte(&self) -> Balance {
+            self.deposit_per_byte
+        }
+
+        /// Remove closed agreement and its index entries, creator only.
+        /// Only seal hash is retained, storage deposit is refunded to creator.
+        #[ink(message)]
+        pub fn archive_agreement(&mut self, index: u64) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let agreement = self._assert_creator(index);
+            let closed = [STATUS_FINISHED, STATUS_SUPERSEDED, STATUS_TERMINATED, STATUS_CANCELLED];
+            assert!(closed.contains(&agreement.status), "agreement not closed");
+            Polkasign::_assert_mutable(agreement);
+            assert!(agreement.escrow.balance == 0 && agreement.escrow.unpaid.is_empty(), "escrow not empty");
+
+            let agreement = self.agreements_map.take(&index).unwrap();
+            if let Some(ids) = self.agreements_creator_map.get_mut(&agreement.creator) {
+                ids.retain(|id| *id != index);
+            }
+            for signer in agreement.signers.iter() {
+                if let Some(ids) = self.agreements_collaborator_map.get_mut(signer) {
+                    ids.retain(|id| *id != index);
                 }
             }
-            let ids = list_res.unwrap();
-            let total = ids.len() as u64;
-            let (start, end, pages) = cal_pages(&pageParams, total);
-            let mut result = Vec::new();
-            for i in start..end {
-                result.push(Polkasign::convAgreement2Display(self.agreements_map.get(&ids[i as usize]).unwrap()));
+            self._unindex_document(agreement.agreement_file.hash, index);
+            for doc in agreement.documents.iter() {
+                self._unindex_document(doc.hash, index);
             }
-            return PageResult{
-                success: true,
-                err: String::from("success"),
-                total,
-                pages,
-                page_index: pageParams.page_index,
-                page_size: pageParams.page_size,
-                data: result,
+            for r in agreement.resources.iter() {
+                self._unindex_document(r.info.hash, index);
+            }
+            if let Some(parent) = agreement.parent.and_then(|link| self.agreements_map.get_mut(&link.index)) {
+                parent.children.retain(|id| *id != index);
             }
+            self.agreements_comment_map.take(&index);
+            self.agreements_envelope_map.take(&index);
+            self.archived_map.insert(index, agreement.seal_hash);
+            if agreement.deposit > 0 {
+                assert!(self.env().transfer(agreement.creator, agreement.deposit).is_ok(), "transfer failed");
+            }
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: caller,
+            });
         }
-    }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        /// Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
-        use std::convert::TryFrom;
+        /// Query seal hash retained for archived agreement, none if it was cancelled before finished.
+        #[ink(message)]
+        pub fn query_archived_seal_hash(&self, index: u64) -> Option<Hash> {
+            *self.archived_map.get(&index).expect("agreement not archived")
+        }
 
-        /// Imports `ink_lang` so we can use `#[ink::test]`.
-        use ink_lang as ink;
+        #[ink(message)]
+        pub fn set_fees(&mut self, create_fee: Balance, signer_fee: Balance) {
+            self._assert_owner();
+            self.create_fee = create_fee;
+            self.signer_fee = signer_fee;
+        }
 
-        #[ink::test]
-        fn new_works() {
-            let test_account :AccountId = [0u8; 32].into();
-            let polkasion = Polkasign::new(test_account);
-            assert_eq!(polkasion.index(), 0);
-            assert_eq!(polkasion.owner(), test_account);
+        /// Query create fee and per signer fee.
+        #[ink(message)]
+        pub fn fees(&self) -> (Balance, Balance) {
+            (self.create_fee, self.signer_fee)
         }
 
-        #[ink::test]
-        fn create_agreement_and_query_agreement_by_id() {
-            let test_account :AccountId = [0u8; 32].into();
-            let mut polkasion = Polkasign::new(test_account);
-            let params = CreateAgreementParams {
-                name: "test".to_string(),
-                signers: vec![[1u8; 32].into()],
-                agreement_file: StorageInfo {
-                    hash: [7u8; 32].into(),
-                    creator: [1u8; 32].into(),
-                    usage: "doc".to_string(),
-                    save_at: "ipfs".to_string(),
-                    url: "http://ipfs.io/xxxx".to_string()
-                }
-            };
-            let index = polkasion.create_agreement(params.clone());
-            assert_eq!(polkasion.index(), index + 1);
-            let res = polkasion.query_agreement_by_id(index);
-            assert_eq!(res.name, params.name);
-            assert_eq!(res.signers, params.signers);
+        #[ink(message)]
+        pub fn add_fee_exempt(&mut self, account: AccountId) {
+            self._assert_owner();
+            let time_at = self.env().block_timestamp();
+            self.fee_exempts.insert(account, time_at);
         }
 
-        #[ink::test]
-        fn create_agreement_and_query_agreement_by_creator() {
-            let test_account :AccountId = [0u8; 32].into();
-            let mut polkasion = Polkasign::new(test_account);
-            let params = CreateAgreementParams {
-                name: "test".to_string(),
-                signers: vec![[1u8; 32].into()],
-                agreement_file: StorageInfo {
-                    hash: [7u8; 32].into(),
-                    creator: [1u8; 32].into(),
-                    usage: "doc".to_string(),
-                    save_at: "ipfs".to_string(),
-                    url: "http://ipfs.io/xxxx".to_string()
-                }
+        #[ink(message)]
+        pub fn remove_fee_exempt(&mut self, account: AccountId) {
+            self._assert_owner();
+            self.fee_exempts.take(&account);
+        }
+
+        #[ink(message)]
+        pub fn is_fee_exempt(&self, account: AccountId) -> bool {
+            self.fee_exempts.contains_key(&account)
+        }
+
+        #[ink(message)]
+        pub fn treasury(&self) -> Balance {
+            self.treasury
+        }
+
+        /// Query value overpaid on create and not withdrawn yet.
+        #[ink(message)]
+        pub fn query_refund(&self, account: AccountId) -> Balance {
+            self.refunds.get(&account).copied().unwrap_or(0)
+        }
+
+        /// Withdraw own value overpaid on create.
+        #[ink(message)]
+        pub fn withdraw_refund(&mut self) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let amount = self.refunds.take(&caller).expect("no refund");
+            assert!(self.env().transfer(caller, amount).is_ok(), "transfer failed");
+        }
+
+        /// Withdraw collected fees, owner only.
+        #[ink(message)]
+        pub fn withdraw(&mut self, to: AccountId, amount: Balance) {
+            self._assert_owner();
+            assert!(amount <= self.treasury, "insufficient treasury");
+            self.treasury -= amount;
+            assert!(self.env().transfer(to, amount).is_ok(), "transfer failed");
+            self.env().emit_event(WithdrawEvent {
+                to,
+                amount,
+            });
+        }
+
+        #[ink(message)]
+        pub fn add_notary(&mut self, notary: AccountId) {
+            self._assert_owner();
+            let time_at = self.env().block_timestamp();
+            self.notaries.insert(notary, time_at);
+        }
+
+        #[ink(message)]
+        pub fn remove_notary(&mut self, notary: AccountId) {
+            self._assert_owner();
+            self.notaries.take(&notary);
+        }
+
+        #[ink(message)]
+        pub fn is_notary(&self, account: AccountId) -> bool {
+            self.notaries.contains_key(&account)
+        }
+
+        #[ink(message)]
+        pub fn attest_signature(&mut self, index: u64, signer: AccountId, evidence: StorageInfo) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let time_at = self.env().block_timestamp();
+            assert!(self.notaries.contains_key(&caller), "not notary");
+            assert!(signer != caller, "notary is signer");
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            // signs are frozen once sealed
+            assert!(Polkasign::_is_open(agreement), "agreement closed");
+            Polkasign::_assert_mutable(agreement);
+            let sign_info = agreement.sign_infos.get_mut(&signer).expect("sign not found");
+            assert!(sign_info.attestation.is_none(), "already attested");
+
+            let mut evidence = evidence;
+            evidence.creator = caller;
+            Polkasign::_validate_storage_info(&evidence);
+            sign_info.attestation = Some(AttestationInfo{
+                notary: caller,
+                evidence,
+                create_at: time_at,
+            });
+            self._try_finish(index);
+            self.env().emit_event(AttestSignatureEvent {
+                index,
+                signer,
+                notary: caller,
+            });
+        }
+
+        fn _document_leaves(agreement_file: &StorageInfo, documents: &[StorageInfo]) -> Vec<[u8; 32]> {
+            let mut leaves = Vec::new();
+            leaves.push(*agreement_file.hash.as_ref());
+            for doc in documents.iter() {
+                leaves.push(*doc.hash.as_ref());
+            }
+            leaves
+        }
+
+        fn _document_root(agreement_file: &StorageInfo, documents: &[StorageInfo]) -> Hash {
+            merkle_root(&Polkasign::_document_leaves(agreement_file, documents)).into()
+        }
+
+        /// Inclusion proof of document in the signed merkle root, agreement file is 0.
+        #[ink(message)]
+        pub fn query_document_proof(&self, index: u64, doc_index: u32) -> DocumentProof {
+            let a = self.agreements_map.get(&index).unwrap();
+            let leaves = Polkasign::_document_leaves(&a.agreement_file, &a.documents);
+            let document = match doc_index {
+                0 => a.agreement_file.clone(),
+                i => a.documents.get(i as usize - 1).expect("document not found").clone(),
             };
-            let index = polkasion.create_agreement(params.clone());
-            assert_eq!(polkasion.index(), index + 1);
-            let res = polkasion.query_agreement_by_creator([1u8; 32].into(), PageParams{
-                page_index: 0,
-                page_size: 10,
+            let proof = merkle_proof(&leaves, doc_index as usize);
+            DocumentProof {
+                document,
+                doc_index,
+                total: leaves.len() as u32,
+                proof: proof.into_iter().map(Hash::from).collect(),
+                root: a.sign_hash,
+            }
+        }
+
+        #[ink(message)]
+        pub fn verify_document_proof(&self, proof: DocumentProof) -> bool {
+            let siblings: Vec<[u8; 32]> = proof.proof.iter().map(|h| *h.as_ref()).collect();
+            verify_merkle_proof(*proof.document.hash.as_ref(), proof.doc_index as usize, proof.total as usize, &siblings, *proof.root.as_ref())
+        }
+
+        /// Prove a document existed, independent of agreements.
+        #[ink(message)]
+        pub fn notarize(&mut self, hash: Hash, info: StorageInfo) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            assert!(!self.notarizations_map.contains_key(&hash), "already notarized");
+            assert!(info.hash == hash, "hash mismatch");
+            Polkasign::_validate_storage_info(&info);
+            let mut info = info;
+            info.creator = caller;
+            self.notarizations_map.insert(hash, NotarizationInfo {
+                hash,
+                submitter: caller,
+                create_at: self.env().block_timestamp(),
+                info,
+                cosigns: Vec::new(),
+            });
+            self.env().emit_event(NotarizeEvent {
+                hash,
+                submitter: caller,
             });
-            assert_eq!(res.total, 1);
-            assert_eq!(res.data[0].name, params.name);
-            assert_eq!(res.data[0].signers, params.signers);
         }
 
-        #[ink::test]
-        fn create_agreement_and_query_agreement_by_collaborator() {
-            let test_account :AccountId = [0u8; 32].into();
-            let mut polkasion = Polkasign::new(test_account);
-            let params = CreateAgreementParams {
-                name: "test".to_string(),
-                signers: vec![[1u8; 32].into(), [2u8; 32].into()],
-                agreement_file: StorageInfo {
-                    hash: [7u8; 32].into(),
-                    creator: [1u8; 32].into(),
-                    usage: "doc".to_string(),
-                    save_at: "ipfs".to_string(),
-                    url: "http://ipfs.io/xxxx".to_string()
+        #[ink(message)]
+        pub fn cosign_notarization(&mut self, hash: Hash, sign: [u8; 64]) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let time_at = self.env().block_timestamp();
+            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *hash.as_ref(), sign), "wrong sign");
+            self._add_cosign(hash, caller, sign, time_at);
+        }
+
+        fn _add_cosign(&mut self, hash: Hash, signer: AccountId, sign: [u8; 64], time_at: u64) {
+            let notarization = self.notarizations_map.get_mut(&hash).expect("not notarized");
+            assert!(notarization.cosigns.iter().all(|s| s.addr != signer), "already cosigned");
+            notarization.cosigns.push(SignInfo {
+                sign: sign.to_vec(),
+                addr: signer,
+                create_at: time_at,
+                attestation: None,
+            });
+        }
+
+        #[ink(message)]
+        pub fn lookup_notarization(&self, hash: Hash) -> Option<NotarizationInfo> {
+            self.notarizations_map.get(&hash).cloned()
+        }
+
+        fn _index_document(&mut self, hash: Hash, index: u64) {
+            match self.agreements_hash_map.get_mut(&hash) {
+                Some(ids) => {
+                    if !ids.contains(&index) {
+                        ids.push(index);
+                    }
+                }
+                None => {
+                    self.agreements_hash_map.insert(hash, alloc::vec![index]);
+                }
+            }
+        }
+
+        #[ink(message)]
+        pub fn post_comment(&mut self, index: u64, reply_to: Option<u64>, content: StorageInfo) -> u64 {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let time_at = self.env().block_timestamp();
+            let agreement = self.agreements_map.get(&index).unwrap();
+            assert!(Polkasign::_is_party(agreement, &caller), "not party of agreement");
+            Polkasign::_assert_not_frozen(agreement);
+
+            let mut content = content;
+            content.creator = caller;
+            Polkasign::_validate_storage_info(&content);
+            let comments = match self.agreements_comment_map.get_mut(&index) {
+                Some(comments) => comments,
+                None => {
+                    self.agreements_comment_map.insert(index, Vec::new());
+                    self.agreements_comment_map.get_mut(&index).unwrap()
                 }
             };
-            let index = polkasion.create_agreement(params.clone());
-            assert_eq!(polkasion.index(), index + 1);
-            let res = polkasion.query_agreement_by_collaborator([2u8; 32].into(), PageParams{
-                page_index: 0,
-                page_size: 10,
+            assert!(comments.len() < Polkasign::MAX_COMMENTS, "too many comments");
+            let id = comments.len() as u64;
+            if let Some(parent) = reply_to {
+                assert!(parent < id, "comment not found");
+            }
+            comments.push(CommentInfo {
+                id,
+                author: caller,
+                reply_to,
+                content,
+                create_at: time_at,
             });
-            assert_eq!(res.total, 1);
-            assert_eq!(res.data[0].name, params.name);
-            assert_eq!(res.data[0].signers, params.signers);
+            self.env().emit_event(CommentEvent {
+                index,
+                id,
+                author: caller,
+                reply_to,
+            });
+            id
         }
 
-        #[ink::test]
-        fn attach_resource_and_query_agreement_by_id() {
-            let test_account :AccountId = [0u8; 32].into();
-            let mut polkasion = Polkasign::new(test_account);
-            let params = CreateAgreementParams {
-                name: "test".to_string(),
-                signers: vec![[1u8; 32].into()],
-                agreement_file: StorageInfo {
-                    hash: [7u8; 32].into(),
-                    creator: [1u8; 32].into(),
-                    usage: "doc".to_string(),
-                    save_at: "ipfs".to_string(),
-                    url: "http://ipfs.io/xxxx".to_string()
+        /// Query comments of agreement in posting order.
+        #[ink(message)]
+        pub fn query_comments(&self, index: u64, page_params: PageParams) -> PageResult<CommentInfo> {
+            let empty = Vec::new();
+            let comments = self.agreements_comment_map.get(&index).unwrap_or(&empty);
+            let total = comments.len() as u64;
+            let (start, end, pages) = cal_pages(&page_params, total);
+            let mut result = Vec::new();
+            for i in start..end {
+                result.push(comments[i as usize].clone());
+            }
+            PageResult {
+                success: true,
+                err: String::from("success"),
+                total,
+                pages,
+                page_index: page_params.page_index,
+                page_size: page_params.page_size,
+                data: result,
+            }
+        }
+
+        /// Query comment with all its replies, in posting order.
+        #[ink(message)]
+        pub fn query_comment_thread(&self, index: u64, comment_id: u64) -> Vec<CommentInfo> {
+            let comments = self.agreements_comment_map.get(&index).expect("comment not found");
+            assert!(comment_id < comments.len() as u64, "comment not found");
+            let mut thread_ids = Vec::new();
+            let mut result = Vec::new();
+            // replies always follow the comment they reply to
+            for comment in comments[comment_id as usize..].iter() {
+                let in_thread = comment.id == comment_id
+                    || comment.reply_to.is_some_and(|parent| thread_ids.contains(&parent));
+                if in_thread {
+                    thread_ids.push(comment.id);
+                    result.push(comment.clone());
                 }
-            };
-            let index = polkasion.create_agreement(params.clone());
-            assert_eq!(polkasion.index(), index + 1);
+            }
+            result
+        }
 
-            // attach resource
-            let info = StorageInfo {
-                hash: [2u8; 32].into(),
-                creator: [1u8; 32].into(),
-                usage: "comment".to_string(),
-                save_at: "ipfs".to_string(),
-                url: "https://ipfs.io/xxxx".to_string()
+        const MAX_CIPHERTEXT_LEN: usize = 1024;
+
+        /// Set the document key envelope of a participant, creator only.
+        #[ink(message)]
+        pub fn set_key_envelope(&mut self, index: u64, envelope: KeyEnvelopeParams) {
+            self._assert_not_paused();
+            let agreement = self._assert_creator(index);
+            Polkasign::_assert_mutable(agreement);
+            assert!(Polkasign::_is_party(agreement, &envelope.participant), "not party of agreement");
+            assert!(envelope.ciphertext.len() <= Polkasign::MAX_CIPHERTEXT_LEN, "ciphertext too long");
+            let time_at = self.env().block_timestamp();
+            let envelopes = match self.agreements_envelope_map.get_mut(&index) {
+                Some(envelopes) => envelopes,
+                None => {
+                    self.agreements_envelope_map.insert(index, BTreeMap::new());
+                    self.agreements_envelope_map.get_mut(&index).unwrap()
+                }
             };
-            polkasion.attach_resource_to_agreement(index, info.clone());
+            let key_version = envelopes.values().map(|e| e.key_version).max().unwrap_or(0);
+            envelopes.insert(envelope.participant, KeyEnvelope {
+                scheme: envelope.scheme,
+                ciphertext: envelope.ciphertext,
+                key_version,
+                update_at: time_at,
+            });
+        }
 
-            let res = polkasion.query_agreement_by_id(index);
-            assert_eq!(res.name, params.name);
-            assert_eq!(res.signers, params.signers);
-            assert_eq!(res.resources[0].hash, info.hash);
+        /// Replace all envelopes with a new document key, envelopes of absent participants are removed.
+        #[ink(message)]
+        pub fn rotate_key_envelopes(&mut self, index: u64, envelopes: Vec<KeyEnvelopeParams>) {
+            self._assert_not_paused();
+            let agreement = self._assert_creator(index);
+            Polkasign::_assert_mutable(agreement);
+            let time_at = self.env().block_timestamp();
+            let key_version = self.agreements_envelope_map.get(&index)
+                .and_then(|e| e.values().map(|e| e.key_version).max())
+                .map_or(0, |v| v + 1);
+            let mut result = BTreeMap::new();
+            for envelope in envelopes {
+                assert!(Polkasign::_is_party(agreement, &envelope.participant), "not party of agreement");
+                assert!(envelope.ciphertext.len() <= Polkasign::MAX_CIPHERTEXT_LEN, "ciphertext too long");
+                result.insert(envelope.participant, KeyEnvelope {
+                    scheme: envelope.scheme,
+                    ciphertext: envelope.ciphertext,
+                    key_version,
+                    update_at: time_at,
+                });
+            }
+            self.agreements_envelope_map.insert(index, result);
+        }
+
+        /// Query the document key envelope of caller.
+        #[ink(message)]
+        pub fn query_key_envelope(&self, index: u64) -> Option<KeyEnvelope> {
+            let caller = self.env().caller();
+            self.agreements_envelope_map.get(&index)
+                .and_then(|e| e.get(&caller))
+                .cloned()
+        }
+
+        #[ink(message)]
+        pub fn url_commitment(&self, url: String, salt: Vec<u8>) -> Hash {
+            let mut input = url.into_bytes();
+            input.extend_from_slice(&salt);
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_bytes::<Blake2x256>(&input, &mut output);
+            output.into()
+        }
+
+        /// Reveal the hidden agreement file url after finished, creator only.
+        #[ink(message)]
+        pub fn reveal_url(&mut self, index: u64, url: String, salt: Vec<u8>) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let agreement = self._assert_creator(index);
+            Polkasign::_assert_mutable(agreement);
+            let commitment = agreement.url_commitment.expect("url not hidden");
+            assert!(agreement.seal_hash.is_some(), "agreement not finished");
+            assert!(agreement.agreement_file.url.is_empty(), "url already revealed");
+            assert!(self.url_commitment(url.clone(), salt) == commitment, "wrong url or salt");
+
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            agreement.agreement_file.url = url;
+            Polkasign::_validate_storage_info(&agreement.agreement_file);
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: caller,
+            });
+        }
+
+        const MAX_TAGS: usize = 8;
+        const MAX_METADATA_FIELDS: usize = 16;
+        const MAX_LABEL_LEN: usize = 32;
+        const MAX_METADATA_VALUE_LEN: usize = 256;
+
+        fn _validate_labels(tags: &[String], metadata: &BTreeMap<String, String>) {
+            assert!(tags.len() <= Polkasign::MAX_TAGS, "too many tags");
+            for (i, tag) in tags.iter().enumerate() {
+                assert!(!tag.is_empty() && tag.len() <= Polkasign::MAX_LABEL_LEN, "wrong tag length");
+                assert!(!tags[..i].contains(tag), "duplicate tag");
+            }
+            assert!(metadata.len() <= Polkasign::MAX_METADATA_FIELDS, "too many metadata fields");
+            for (key, value) in metadata.iter() {
+                assert!(!key.is_empty() && key.len() <= Polkasign::MAX_LABEL_LEN, "wrong metadata key length");
+                assert!(value.len() <= Polkasign::MAX_METADATA_VALUE_LEN, "metadata value too long");
+            }
+        }
+
+        /// Replace tags and metadata while agreement is draft, creator only.
+        #[ink(message)]
+        pub fn update_agreement_labels(&mut self, index: u64, tags: Vec<String>, metadata: BTreeMap<String, String>) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let agreement = self._assert_creator(index);
+            assert!(agreement.status == STATUS_INIT, "agreement not draft");
+            Polkasign::_assert_mutable(agreement);
+            Polkasign::_validate_labels(&tags, &metadata);
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            agreement.tags = tags;
+            agreement.metadata = metadata;
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: caller,
+            });
+        }
+
+        /// Query agreements caller created or collaborates on, with the tag.
+        #[ink(message)]
+        pub fn query_my_agreements_by_tag(&self, tag: String, page_params: PageParams) -> PageResult<AgreementInfoDisplay> {
+            let caller = self.env().caller();
+            self._query_agreements_of(caller, |a| a.tags.contains(&tag), page_params)
+        }
+
+        /// Query agreements caller created or collaborates on, with the metadata key.
+        #[ink(message)]
+        pub fn query_my_agreements_by_metadata_key(&self, key: String, page_params: PageParams) -> PageResult<AgreementInfoDisplay> {
+            let caller = self.env().caller();
+            self._query_agreements_of(caller, |a| a.metadata.contains_key(&key), page_params)
+        }
+
+        fn _query_agreements_of<F>(&self, account: AccountId, filter: F, page_params: PageParams) -> PageResult<AgreementInfoDisplay>
+            where F: Fn(&AgreementInfo) -> bool
+        {
+            let mut ids: Vec<u64> = Vec::new();
+            for list in [self.agreements_creator_map.get(&account), self.agreements_collaborator_map.get(&account)].iter().flatten() {
+                ids.extend(list.iter());
+            }
+            ids.sort_unstable();
+            ids.dedup();
+            let list: Vec<&AgreementInfo> = ids.iter()
+                .filter_map(|id| self.agreements_map.get(id))
+                .filter(|a| filter(a))
+                .collect();
+            let total = list.len() as u64;
+            let (start, end, pages) = cal_pages(&page_params, total);
+            let mut result = Vec::new();
+            for i in start..end {
+                result.push(Polkasign::convAgreement2Display(list[i as usize]));
+            }
+            PageResult {
+                success: true,
+                err: String::from("success"),
+                total,
+                pages,
+                page_index: page_params.page_index,
+                page_size: page_params.page_size,
+                data: result,
+            }
+        }
+
+        fn _assert_creator(&self, index: u64) -> &AgreementInfo {
+            let agreement = self.agreements_map.get(&index).unwrap();
+            assert!(agreement.creator == self.env().caller(), "not creator");
+            agreement
+        }
+
+        fn _validate_beneficiaries(beneficiaries: &[Beneficiary]) {
+            for (i, b) in beneficiaries.iter().enumerate() {
+                assert!(b.share > 0, "wrong share");
+                assert!(beneficiaries[..i].iter().all(|o| o.account != b.account), "duplicate beneficiary");
+            }
+        }
+
+        /// Replace escrow beneficiaries while agreement is draft, creator only.
+        #[ink(message)]
+        pub fn set_escrow_beneficiaries(&mut self, index: u64, beneficiaries: Vec<Beneficiary>) {
+            self._assert_not_paused();
+            let agreement = self._assert_creator(index);
+            assert!(agreement.status == STATUS_INIT, "agreement not draft");
+            Polkasign::_assert_mutable(agreement);
+            assert!(agreement.escrow.balance == 0, "escrow not empty");
+            assert!(!beneficiaries.is_empty(), "no beneficiaries");
+            Polkasign::_validate_beneficiaries(&beneficiaries);
+            self.agreements_map.get_mut(&index).unwrap().escrow.beneficiaries = beneficiaries;
+        }
+
+        /// Deposit transferred value into escrow, released to beneficiaries when finished.
+        #[ink(message, payable)]
+        pub fn deposit_escrow(&mut self, index: u64) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let value = self.env().transferred_balance();
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            assert!(Polkasign::_is_party(agreement, &caller), "not party of agreement");
+            assert!(Polkasign::_is_open(agreement), "agreement closed");
+            Polkasign::_assert_mutable(agreement);
+            assert!(!agreement.escrow.beneficiaries.is_empty(), "no beneficiaries");
+            assert!(value > 0, "zero deposit");
+            *agreement.escrow.deposits.entry(caller).or_insert(0) += value;
+            agreement.escrow.balance += value;
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: caller,
+            });
+        }
+
+        /// Release escrow to beneficiaries by share, remainder goes to the last one.
+        fn _release_escrow(&mut self, index: u64) {
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            let escrow = &mut agreement.escrow;
+            if escrow.released || escrow.balance == 0 {
+                return;
+            }
+            let total_share: Balance = escrow.beneficiaries.iter().map(|b| b.share as Balance).sum();
+            let mut payments = Vec::new();
+            let mut remaining = escrow.balance;
+            for (i, b) in escrow.beneficiaries.iter().enumerate() {
+                let amount = if i + 1 == escrow.beneficiaries.len() {
+                    remaining
+                } else {
+                    escrow.balance / total_share * b.share as Balance
+                        + escrow.balance % total_share * b.share as Balance / total_share
+                };
+                remaining -= amount;
+                payments.push((b.account, amount));
+            }
+            escrow.balance = 0;
+            escrow.released = true;
+            let mut unpaid = Vec::new();
+            for (account, amount) in payments {
+                if amount > 0 && self.env().transfer(account, amount).is_err() {
+                    unpaid.push((account, amount));
+                }
+            }
+            // failed payout must not revert the sign finishing agreement
+            let escrow = &mut self.agreements_map.get_mut(&index).unwrap().escrow;
+            for (account, amount) in unpaid {
+                *escrow.unpaid.entry(account).or_insert(0) += amount;
+            }
+        }
+
+        /// Withdraw escrow payout failed on release, beneficiary only.
+        #[ink(message)]
+        pub fn withdraw_escrow_payout(&mut self, index: u64) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            Polkasign::_assert_mutable(agreement);
+            let amount = agreement.escrow.unpaid.remove(&caller).expect("no unpaid payout");
+            assert!(self.env().transfer(caller, amount).is_ok(), "transfer failed");
+        }
+
+        /// Cancel agreement before finished, creator only.
+        #[ink(message)]
+        pub fn cancel_agreement(&mut self, index: u64) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let agreement = self._assert_creator(index);
+            assert!(Polkasign::_is_open(agreement), "agreement closed");
+            Polkasign::_assert_mutable(agreement);
+            self.agreements_map.get_mut(&index).unwrap().status = STATUS_CANCELLED;
+            self._notify_callback(index);
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: caller,
+            });
         }
+
+        /// Reclaim own deposit after agreement cancelled, or expired before finished.
+        #[ink(message)]
+        pub fn reclaim_escrow(&mut self, index: u64) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let now = self.env().block_timestamp();
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            let expired = Polkasign::_is_open(agreement) && agreement.terminates_at.is_some_and(|t| now >= t);
+            assert!(agreement.status == STATUS_CANCELLED || expired, "agreement not cancelled or expired");
+            Polkasign::_assert_mutable(agreement);
+            assert!(!agreement.escrow.released, "escrow released");
+            let amount = agreement.escrow.deposits.remove(&caller).expect("no deposit");
+            agreement.escrow.balance -= amount;
+            assert!(self.env().transfer(caller, amount).is_ok(), "transfer failed");
+        }
+
+        /// The hash payee signs to deliver and payer signs to approve a milestone.
+        #[ink(message)]
+        pub fn milestone_hash(&self, index: u64, milestone_id: u32) -> Hash {
+            let a = self.agreements_map.get(&index).unwrap();
+            let seal_hash = a.seal_hash.expect("agreement not finished");
+            let m = a.milestones.get(milestone_id as usize).expect("milestone not found");
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink_env::hash_encoded::<Blake2x256, _>(&(b"milestone", seal_hash, m.id, m.token, m.payee, m.amount), &mut output);
+            output.into()
+        }
+
+        /// Mark milestone delivered, payee only.
+        #[ink(message)]
+        pub fn deliver_milestone(&mut self, index: u64, milestone_id: u32, sign: [u8; 64]) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let time_at = self.env().block_timestamp();
+            let msg = self.milestone_hash(index, milestone_id);
+            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *msg.as_ref(), sign), "wrong sign");
+            self._deliver_milestone(index, milestone_id, caller, sign, time_at);
+        }
+
+        /// Approve delivered milestone, payer only.
+        /// Token is transferred from payer to payee, payer must allow this contract to spend it.
+        #[ink(message)]
+        pub fn approve_milestone(&mut self, index: u64, milestone_id: u32, sign: [u8; 64]) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let time_at = self.env().block_timestamp();
+            let msg = self.milestone_hash(index, milestone_id);
+            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), *msg.as_ref(), sign), "wrong sign");
+            let m = self._approve_milestone(index, milestone_id, caller, sign, time_at);
+            assert!(token_transfer_from(m.token, m.payer, m.payee, m.amount), "token transfer failed");
+        }
+
+        fn _assert_milestone_payable(a: &AgreementInfo, milestone_id: u32) {
+            assert!(a.status == STATUS_FINISHED, "agreement not finished");
+            Polkasign::_assert_mutable(a);
+            assert!(a.milestones.len() > milestone_id as usize, "milestone not found");
+        }
+
+        fn _deliver_milestone(&mut self, index: u64, milestone_id: u32, signer: AccountId, sign: [u8; 64], time_at: u64) {
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            Polkasign::_assert_milestone_payable(agreement, milestone_id);
+            let m = &mut agreement.milestones[milestone_id as usize];
+            assert!(m.payee == signer, "not payee");
+            assert!(m.delivered.is_none(), "milestone delivered");
+            m.delivered = Some(SignInfo {
+                sign: sign.to_vec(),
+                addr: signer,
+                create_at: time_at,
+                attestation: None,
+            });
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: signer,
+            });
+        }
+
+        fn _approve_milestone(&mut self, index: u64, milestone_id: u32, signer: AccountId, sign: [u8; 64], time_at: u64) -> MilestoneInfo {
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            Polkasign::_assert_milestone_payable(agreement, milestone_id);
+            let m = &mut agreement.milestones[milestone_id as usize];
+            assert!(m.payer == signer, "not payer");
+            assert!(m.delivered.is_some(), "milestone not delivered");
+            assert!(m.approved.is_none(), "milestone approved");
+            m.approved = Some(SignInfo {
+                sign: sign.to_vec(),
+                addr: signer,
+                create_at: time_at,
+                attestation: None,
+            });
+            let m = m.clone();
+            self.env().emit_event(UpdateAgreementEvent {
+                index,
+                creator: signer,
+            });
+            m
+        }
+
+        /// Mint a certificate token per participant of finished agreement, creator first then signers.
+        fn _mint_certificates(&mut self, index: u64) {
+            let agreement = self.agreements_map.get(&index).unwrap();
+            let seal_hash = agreement.seal_hash.unwrap();
+            let (mint_at, transferable) = (agreement.finish_at, agreement.transferable_certificates);
+            let mut owners = alloc::vec![agreement.creator];
+            owners.extend(agreement.signers.iter().filter(|s| **s != agreement.creator));
+            for owner in owners {
+                let token_id = self.certificate_index;
+                self.certificate_index += 1;
+                self.certificates_map.insert(token_id, CertificateToken {
+                    token_id,
+                    owner,
+                    index,
+                    seal_hash,
+                    mint_at,
+                    transferable,
+                });
+                self._push_certificate(owner, token_id);
+                self.env().emit_event(CertificateTransferEvent {
+                    token_id,
+                    from: None,
+                    to: owner,
+                });
+            }
+        }
+
+        fn _push_certificate(&mut self, owner: AccountId, token_id: u64) {
+            match self.certificates_owner_map.get_mut(&owner) {
+                Some(ids) => ids.push(token_id),
+                None => {
+                    self.certificates_owner_map.insert(owner, alloc::vec![token_id]);
+                }
+            }
+        }
+
+        /// Call the callback of agreement, failure is recorded instead of reverting.
+        fn _notify_callback(&mut self, index: u64) {
+            let call_at = self.env().block_timestamp();
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            let callback = match &agreement.callback {
+                Some(callback) => callback,
+                None => return,
+            };
+            let seal_hash = if agreement.status == STATUS_FINISHED { agreement.seal_hash } else { None };
+            let success = invoke_callback(callback, index, seal_hash);
+            agreement.callback_results.push(CallbackResult {
+                status: agreement.status,
+                success,
+                call_at,
+            });
+        }
+
+        /// Transfer certificate token, only if agreement allows transferable certificates.
+        #[ink(message)]
+        pub fn transfer_certificate(&mut self, token_id: u64, to: AccountId) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let token = self.certificates_map.get_mut(&token_id).expect("certificate not found");
+            assert!(token.owner == caller, "not owner of certificate");
+            assert!(token.transferable, "certificate not transferable");
+            token.owner = to;
+            let ids = self.certificates_owner_map.get_mut(&caller).unwrap();
+            ids.retain(|id| *id != token_id);
+            self._push_certificate(to, token_id);
+            self.env().emit_event(CertificateTransferEvent {
+                token_id,
+                from: Some(caller),
+                to,
+            });
+        }
+
+        #[ink(message)]
+        pub fn query_certificate_token(&self, token_id: u64) -> Option<CertificateToken> {
+            self.certificates_map.get(&token_id).cloned()
+        }
+
+        /// Query certificate tokens held by account.
+        #[ink(message)]
+        pub fn query_certificates_of(&self, owner: AccountId, page_params: PageParams) -> PageResult<CertificateToken> {
+            let empty = Vec::new();
+            let ids = self.certificates_owner_map.get(&owner).unwrap_or(&empty);
+            let total = ids.len() as u64;
+            let (start, end, pages) = cal_pages(&page_params, total);
+            let mut result = Vec::new();
+            for i in start..end {
+                result.push(self.certificates_map.get(&ids[i as usize]).unwrap().clone());
+            }
+            PageResult {
+                success: true,
+                err: String::from("success"),
+                total,
+                pages,
+                page_index: page_params.page_index,
+                page_size: page_params.page_size,
+                data: result,
+            }
+        }
+
+        /// Changes are blocked while disputed or frozen.
+        fn _assert_mutable(a: &AgreementInfo) {
+            Polkasign::_assert_not_frozen(a);
+            assert!(a.status != STATUS_DISPUTED, "agreement disputed");
+        }
+
+        /// Legal hold blocks all changes, including comments and disputes.
+        fn _assert_not_frozen(a: &AgreementInfo) {
+            assert!(!a.frozen, "agreement frozen");
+        }
+
+        #[ink(message)]
+        pub fn raise_dispute(&mut self, index: u64, evidence: StorageInfo) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let time_at = self.env().block_timestamp();
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            assert!(Polkasign::_is_party(agreement, &caller), "not party of agreement");
+            assert!(agreement.arbiter.is_some(), "no arbiter");
+            Polkasign::_assert_mutable(agreement);
+            assert!(agreement.status != STATUS_CANCELLED, "agreement cancelled");
+            assert!(agreement.disputes.len() < Polkasign::MAX_DISPUTES, "too many disputes");
+
+            let mut evidence = evidence;
+            evidence.creator = caller;
+            Polkasign::_validate_storage_info(&evidence);
+            agreement.disputes.push(DisputeInfo {
+                raiser: caller,
+                evidence,
+                raise_at: time_at,
+                prev_status: agreement.status,
+                outcome: None,
+                ruling: None,
+                resolve_at: 0,
+            });
+            agreement.status = STATUS_DISPUTED;
+            self.env().emit_event(DisputeRaisedEvent {
+                index,
+                raiser: caller,
+            });
+        }
+
+        /// Resolve the dispute, arbiter only. Upheld restores status, voided cancels agreement.
+        #[ink(message)]
+        pub fn resolve_dispute(&mut self, index: u64, outcome: DisputeOutcome, ruling: StorageInfo) {
+            self._assert_not_paused();
+            let caller = self.env().caller();
+            let time_at = self.env().block_timestamp();
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            assert!(agreement.arbiter == Some(caller), "not arbiter");
+            assert!(agreement.status == STATUS_DISPUTED, "agreement not disputed");
+            Polkasign::_assert_not_frozen(agreement);
+
+            let mut ruling = ruling;
+            ruling.creator = caller;
+            Polkasign::_validate_storage_info(&ruling);
+            let dispute = agreement.disputes.last_mut().unwrap();
+            dispute.outcome = Some(outcome);
+            dispute.ruling = Some(ruling);
+            dispute.resolve_at = time_at;
+            agreement.status = match outcome {
+                DisputeOutcome::Upheld => dispute.prev_status,
+                DisputeOutcome::Voided => STATUS_CANCELLED,
+            };
+            self.env().emit_event(DisputeResolvedEvent {
+                index,
+                arbiter: caller,
+                outcome,
+            });
+            match outcome {
+                // a child superseding or terminating it may have finished meanwhile
+                DisputeOutcome::Upheld => self._apply_child_links(index),
+                DisputeOutcome::Voided => self._notify_callback(index),
+            }
+        }
+
+        fn _is_party(a: &AgreementInfo, account: &AccountId) -> bool {
+            a.creator == *account || a.signers.contains(account)
+        }
+
+        fn _unindex_document(&mut self, hash: Hash, index: u64) {
+            if let Some(ids) = self.agreements_hash_map.get_mut(&hash) {
+                ids.retain(|id| *id != index);
+                if ids.is_empty() {
+                    self.agreements_hash_map.take(&hash);
+                }
+            }
+        }
+
+        /// Whether agreement file, documents or unretracted resources reference the hash.
+        fn _references_document(a: &AgreementInfo, hash: &Hash) -> bool {
+            a.agreement_file.hash == *hash
+                || a.documents.iter().any(|d| d.hash == *hash)
+                || a.resources.iter().any(|r| r.retraction.is_none() && r.info.hash == *hash)
+        }
+
+        // bound what a party can add after the storage deposit is charged
+        const MAX_USAGE_LEN: usize = 64;
+        const MAX_URL_LEN: usize = 512;
+        const MAX_REASON_LEN: usize = 256;
+
+        /// Reject oversized or malformed reference, ipfs cid digest must match hash for sha2-256 and blake2b-256.
+        fn _validate_storage_info(info: &StorageInfo) {
+            assert!(info.usage.len() <= Polkasign::MAX_USAGE_LEN, "usage too long");
+            assert!(info.url.len() <= Polkasign::MAX_URL_LEN, "url too long");
+            if info.save_at != StorageBackend::Ipfs {
+                return;
+            }
+            let (cid, sub_path) = parse_ipfs_url(&info.url).expect("invalid cid");
+            if sub_path {
+                return;
+            }
+            let cid_algo = match cid.hash_code {
+                SHA2_256 => HashAlgorithm::Sha2x256,
+                BLAKE2B_256 => HashAlgorithm::Blake2x256,
+                _ => return,
+            };
+            assert!(cid_algo == info.hash_algo, "hash algorithm mismatch");
+            let hash: &[u8] = info.hash.as_ref();
+            assert!(cid.digest == hash, "cid hash mismatch");
+        }
+
+        const MAX_INLINE_PAYLOAD_LEN: usize = 4096;
+
+        /// Recompute the hash of a small inline payload, so verifiers can reproduce the digest.
+        #[ink(message)]
+        pub fn compute_hash(&self, algo: HashAlgorithm, data: Vec<u8>) -> Hash {
+            assert!(data.len() <= Polkasign::MAX_INLINE_PAYLOAD_LEN, "payload too long");
+            let mut output = [0u8; 32];
+            match algo {
+                HashAlgorithm::Sha2x256 => ink_env::hash_bytes::<Sha2x256>(&data, &mut output),
+                HashAlgorithm::Blake2x256 => ink_env::hash_bytes::<Blake2x256>(&data, &mut output),
+                HashAlgorithm::Keccak256 => ink_env::hash_bytes::<Keccak256>(&data, &mut output),
+            }
+            output.into()
+        }
+
+        fn _assert_owner(&self) {
+            assert!(self.env().caller() == self.owner, "not owner");
+        }
+
+        fn _assert_not_paused(&self) {
+            assert!(!self.paused, "contract paused");
+        }
+
+        /// Start ownership transfer, takes effect once accepted by new owner.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) {
+            self._assert_owner();
+            self.pending_owner = Some(new_owner);
+            self.env().emit_event(OwnershipTransferEvent {
+                from: self.owner,
+                to: new_owner,
+                accepted: false,
+            });
+        }
+
+        #[ink(message)]
+        pub fn accept_ownership(&mut self) {
+            let caller = self.env().caller();
+            assert!(self.pending_owner == Some(caller), "not pending owner");
+            let from = self.owner;
+            self.owner = caller;
+            self.pending_owner = None;
+            self.env().emit_event(OwnershipTransferEvent {
+                from,
+                to: caller,
+                accepted: true,
+            });
+        }
+
+        #[ink(message)]
+        pub fn pending_owner(&self) -> Option<AccountId> {
+            self.pending_owner
+        }
+
+        /// Block state-changing messages, queries keep working.
+        #[ink(message)]
+        pub fn pause(&mut self) {
+            self._assert_owner();
+            assert!(!self.paused, "contract paused");
+            self.paused = true;
+            self.env().emit_event(PauseEvent { paused: true });
+        }
+
+        #[ink(message)]
+        pub fn unpause(&mut self) {
+            self._assert_owner();
+            assert!(self.paused, "contract not paused");
+            self.paused = false;
+            self.env().emit_event(PauseEvent { paused: false });
+        }
+
+        #[ink(message)]
+        pub fn paused(&self) -> bool {
+            self.paused
+        }
+
+        /// Legal hold of agreement, blocks its changes until unfrozen, owner only.
+        #[ink(message)]
+        pub fn freeze_agreement(&mut self, index: u64) {
+            self._set_frozen(index, true);
+        }
+
+        #[ink(message)]
+        pub fn unfreeze_agreement(&mut self, index: u64) {
+            self._set_frozen(index, false);
+            self._apply_child_links(index);
+        }
+
+        fn _set_frozen(&mut self, index: u64, frozen: bool) {
+            self._assert_owner();
+            let agreement = self.agreements_map.get_mut(&index).unwrap();
+            assert!(agreement.frozen != frozen, "frozen unchanged");
+            agreement.frozen = frozen;
+            self.env().emit_event(FreezeEvent { index, frozen });
+        }
+
+        #[ink(message)]
+        pub fn check_sr25519_sign(&mut self, msg: [u8; 32], sign: [u8; 64]) -> bool {
+            let caller = self.env().caller();
+            assert!(self._check_sr25519_sign(*caller.as_ref(), msg, sign), "wrong sign");
+            true
+        }
+
+        #[ink(message)]
+        pub fn check_sr25519_bytes_sign(&mut self, msg: [u8; 32], sign: [u8; 64]) -> bool {
+            let caller = self.env().caller();
+            assert!(self._check_sr25519_bytes_sign(*caller.as_ref(), msg, sign), "wrong sign");
+            true
+        }
+
+        pub fn _check_sr25519_sign(&self, public: [u8; 32], msg: [u8; 32], sign: [u8; 64]) -> bool {
+            let res = self.env().extension().verify_sr25519(public, msg, sign);
+            if res.is_ok() {
+                return true;
+            }
+
+            return false
+        }
+
+        const bytes_pre: [char; 7] = ['<', 'B', 'y', 't', 'e', 's', '>'];
+        const bytes_sub: [char; 8] = ['<', '/', 'B', 'y', 't', 'e', 's', '>'];
+        pub fn _check_sr25519_bytes_sign(&self, public: [u8; 32], msg: [u8; 32], sign: [u8; 64]) -> bool {
+            let mut tmp = [0; 47];
+            let mut index = 0;
+            for ch in Polkasign::bytes_pre {
+                tmp[index.clone()] = ch as u8;
+                index += 1;
+            }
+            for ch in msg {
+                tmp[index.clone()] = ch;
+                index += 1;
+            }
+            for ch in Polkasign::bytes_sub {
+                tmp[index.clone()] = ch as u8;
+                index += 1;
+            }
+            let res = self.env().extension().verify_sr25519_bytes(public, tmp, sign);
+            if res.is_ok() {
+                return true;
+            }
+
+            return false
+        }
+
+        #[ink(message)]
+        pub fn query_agreement_by_creator(&mut self, creator: AccountId, pageParams: PageParams) -> PageResult<AgreementInfoDisplay> {
+            let list_res = self.agreements_creator_map.get(&creator);
+            if list_res.is_none() {
+                return PageResult{
+                    success: true,
+                    err: String::from("success"),
+                    total: 0,
+                    pages: 0,
+                    page_index: 0,
+                    page_size: 0,
+                    data: Vec::new(),
+                }
+            }
+            let ids = list_res.unwrap();
+            let total = ids.len() as u64;
+            let (start, end, pages) = cal_pages(&pageParams, total);
+            let mut result = Vec::new();
+            for i in start..end {
+                result.push(Polkasign::convAgreement2Display(self.agreements_map.get(&ids[i as usize]).unwrap()));
+            }
+            return PageResult{
+                success: true,
+                err: String::from("success"),
+                total,
+                pages,
+                page_index: pageParams.page_index,
+                page_size: pageParams.page_size,
+                data: result,
+            }
+        }
+
+        #[ink(message)]
+        pub fn query_agreement_by_id(&mut self, index: u64) -> AgreementInfoDisplay {
+            let a = self.agreements_map.get(&index).unwrap();
+            Polkasign::convAgreement2Display(a)
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn index(&self) -> u64 {
+            self.index.clone()
+        }
+
+        fn convAgreement2Display(a: &AgreementInfo) -> AgreementInfoDisplay {
+            let sign_infos = a.sign_infos.values().cloned().collect();
+            let resources = a.resources.iter()
+                .filter(|r| r.retraction.is_none())
+                .map(|r| r.info.clone())
+                .collect();
+
+            let mut clauses = Vec::new();
+            for clause in a.clauses.iter() {
+                let mut accepted_by = Vec::new();
+                let mut rejected_by = Vec::new();
+                for (account, decisions) in a.clause_decisions.iter() {
+                    match decisions.get(&clause.id) {
+                        Some(true) => accepted_by.push(*account),
+                        Some(false) => rejected_by.push(*account),
+                        None => {}
+                    }
+                }
+                clauses.push(ClauseResult {
+                    clause: clause.clone(),
+                    accepted_by,
+                    rejected_by,
+                });
+            }
+
+            AgreementInfoDisplay {
+                index: a.index,
+                creator: a.creator,
+                name: a.name.clone(),
+                create_at: a.create_at,
+                status: a.status,
+                signers: a.signers.clone(),
+                agreement_file: a.agreement_file.clone(),
+                documents: a.documents.clone(),
+                sign_hash: a.sign_hash,
+                require_attestation: a.require_attestation,
+                sign_infos,
+                resources,
+                clauses,
+                finish_at: a.finish_at,
+                seal_hash: a.seal_hash,
+                url_commitment: a.url_commitment,
+                tags: a.tags.clone(),
+                metadata: a.metadata.clone(),
+                parent: a.parent.clone(),
+                children: a.children.clone(),
+                effective_from: a.effective_from,
+                terminates_at: a.terminates_at,
+                terminate_at: a.terminate_at,
+                termination_signs: a.termination_signs.values().cloned().collect(),
+                arbiter: a.arbiter,
+                disputes: a.disputes.clone(),
+                escrow: a.escrow.clone(),
+                milestones: a.milestones.clone(),
+                transferable_certificates: a.transferable_certificates,
+                callback: a.callback.clone(),
+                callback_results: a.callback_results.clone(),
+                deposit: a.deposit,
+                frozen: a.frozen,
+            }
+        }
+
+        #[ink(message)]
+        pub fn query_agreement_by_collaborator(&mut self, collaborator: AccountId, pageParams: PageParams) -> PageResult<AgreementInfoDisplay> {
+            let list_res = self.agreements_collaborator_map.get(&collaborator);
+            if list_res.is_none() {
+                return PageResult{
+                    success: true,
+                    err: String::from("success"),
+                    total: 0,
+                    pages: 0,
+                    page_index: 0,
+                    page_size: 0,
+                    data: Vec::new(),
+                }
+            }
+            let ids = list_res.unwrap();
+            let total = ids.len() as u64;
+            let (start, end, pages) = cal_pages(&pageParams, total);
+            let mut result = Vec::new();
+            for i in start..end {
+                result.push(Polkasign::convAgreement2Display(self.agreements_map.get(&ids[i as usize]).unwrap()));
+            }
+            return PageResult{
+                success: true,
+                err: String::from("success"),
+                total,
+                pages,
+                page_index: pageParams.page_index,
+                page_size: pageParams.page_size,
+                data: result,
+            }
+        }
+    }
+
+    /// Call erc20 `transfer_from` of token contract, returns whether it succeeded.
+    #[cfg(not(test))]
+    fn token_transfer_from(token: AccountId, from: AccountId, to: AccountId, value: Balance) -> bool {
+        use ink_env::call::{ExecutionInput, Selector};
+        /// Selector of erc20 `transfer_from(from, to, value)`, blake2b("transfer_from")[..4].
+        const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0b, 0x39, 0x6f, 0x18];
+        let result = ink_env::call::build_call::<Environment>()
+            .callee(token)
+            .gas_limit(0)
+            .exec_input(
+                ExecutionInput::new(Selector::new(TRANSFER_FROM_SELECTOR))
+                    .push_arg(from)
+                    .push_arg(to)
+                    .push_arg(value),
+            )
+            // erc20 error is a fieldless enum, encoded as its variant index
+            .returns::<ink_env::call::utils::ReturnType<Result<(), u8>>>()
+            .fire();
+        matches!(result, Ok(Ok(())))
+    }
+
+    /// Off-chain environment can't evaluate contracts, tests stub the token.
+    #[cfg(test)]
+    fn token_transfer_from(token: AccountId, from: AccountId, to: AccountId, value: Balance) -> bool {
+        tests::stub_token_transfer_from(token, from, to, value)
+    }
+
+    /// Call the callback message with (index, seal_hash), returns whether it succeeded.
+    #[cfg(not(test))]
+    fn invoke_callback(callback: &CallbackInfo, index: u64, seal_hash: Option<Hash>) -> bool {
+        use ink_env::call::{ExecutionInput, Selector};
+        let params = ink_env::call::build_call::<Environment>()
+            .callee(callback.callee)
+            .gas_limit(callback.gas_limit)
+            .exec_input(
+                ExecutionInput::new(Selector::new(callback.selector))
+                    .push_arg(index)
+                    .push_arg(seal_hash),
+            )
+            .returns::<()>()
+            .params();
+        ink_env::invoke_contract(&params).is_ok()
+    }
+
+    /// Off-chain environment can't invoke contracts, tests stub the callee.
+    #[cfg(test)]
+    fn invoke_callback(callback: &CallbackInfo, index: u64, seal_hash: Option<Hash>) -> bool {
+        tests::stub_invoke_callback(callback, index, seal_hash)
+    }
+
+    impl AgreementRegistry for Polkasign {
+        #[ink(message, payable)]
+        fn create(&mut self, params: CreateAgreementParams) -> u64 {
+            self.create_agreement(params)
+        }
+
+        #[ink(message)]
+        fn sign(&mut self, index: u64, sign: [u8; 64]) {
+            self.sign_with_clauses(index, Vec::new(), sign);
+        }
+
+        #[ink(message)]
+        fn status(&self, index: u64) -> LifecycleStatus {
+            self.query_lifecycle_status(index)
+        }
+
+        /// Find every agreement referencing the document, to check authenticity of a received file.
+        #[ink(message)]
+        fn verify_document(&self, hash: Hash) -> Vec<DocumentVerifyInfo> {
+            let now = self.env().block_timestamp();
+            let mut result = Vec::new();
+            let ids = match self.agreements_hash_map.get(&hash) {
+                Some(ids) => ids,
+                None => return result,
+            };
+            for id in ids.iter() {
+                let a = self.agreements_map.get(id).unwrap();
+                result.push(DocumentVerifyInfo {
+                    index: a.index,
+                    name: a.name.clone(),
+                    creator: a.creator,
+                    status: a.status,
+                    signers: a.signers.clone(),
+                    sign_infos: a.sign_infos.values().cloned().collect(),
+                    seal_hash: a.seal_hash,
+                    lifecycle: Polkasign::_lifecycle_status(a, now),
+                });
+            }
+            result
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+        use std::convert::TryFrom;
+
+        /// Imports `ink_lang` so we can use `#[ink::test]`.
+        use ink_lang as ink;
+        use std::cell::{Cell, RefCell};
+
+        // stubbed callback call: (callee, selector, index, seal_hash)
+        type CallbackCall = (AccountId, [u8; 4], u64, Option<Hash>);
+
+        thread_local! {
+            // whether stubbed callback calls fail
+            static CALLBACK_FAILS: Cell<bool> = const { Cell::new(false) };
+            static CALLBACK_CALLS: RefCell<Vec<CallbackCall>> = const { RefCell::new(Vec::new()) };
+        }
+
+        thread_local! {
+            // stubbed erc20 transfer_from calls: (token, from, to, value)
+            static TOKEN_TRANSFERS: RefCell<Vec<(AccountId, AccountId, AccountId, Balance)>> = const { RefCell::new(Vec::new()) };
+        }
+
+        pub(super) fn stub_token_transfer_from(token: AccountId, from: AccountId, to: AccountId, value: Balance) -> bool {
+            TOKEN_TRANSFERS.with(|transfers| transfers.borrow_mut().push((token, from, to, value)));
+            true
+        }
+
+        /// Mock of `CryptoExtension`, a sign is valid if it is `test_sign(account, msg)`.
+        struct MockCryptoExtension {
+            func_id: u32,
+        }
+
+        impl ink_env::test::ChainExtension for MockCryptoExtension {
+            fn func_id(&self) -> u32 {
+                self.func_id
+            }
+
+            fn call(&mut self, input: &[u8], _output: &mut Vec<u8>) -> u32 {
+                // input: account ++ msg ++ sign, msg is wrapped in <Bytes></Bytes> by verify_sr25519_bytes
+                let (account, rest) = input.split_at(32);
+                let (msg, sign) = rest.split_at(rest.len() - 64);
+                let msg = if msg.len() == 32 { msg } else { &msg[Polkasign::bytes_pre.len()..][..32] };
+                if sign[..32] == *account && sign[32..] == *msg { 0 } else { 1 }
+            }
+        }
+
+        fn register_crypto_extension() {
+            ink_env::test::register_chain_extension(MockCryptoExtension { func_id: 1102 });
+            ink_env::test::register_chain_extension(MockCryptoExtension { func_id: 1103 });
+        }
+
+        fn test_sign(account: AccountId, msg: Hash) -> [u8; 64] {
+            let mut sign = [0u8; 64];
+            sign[..32].copy_from_slice(account.as_ref());
+            sign[32..].copy_from_slice(msg.as_ref());
+            sign
+        }
+
+        pub(super) fn stub_invoke_callback(callback: &CallbackInfo, index: u64, seal_hash: Option<Hash>) -> bool {
+            CALLBACK_CALLS.with(|calls| calls.borrow_mut().push((callback.callee, callback.selector, index, seal_hash)));
+            !CALLBACK_FAILS.with(|fails| fails.get())
+        }
+
+        #[ink::test]
+        fn new_works() {
+            let test_account :AccountId = [0u8; 32].into();
+            let polkasion = Polkasign::new(test_account);
+            assert_eq!(polkasion.index(), 0);
+            assert_eq!(polkasion.owner(), test_account);
+        }
+
+        #[ink::test]
+        fn create_agreement_and_query_agreement_by_id() {
+            let test_account :AccountId = [0u8; 32].into();
+            let mut polkasion = Polkasign::new(test_account);
+            let params = CreateAgreementParams {
+                name: "test".to_string(),
+                signers: vec![[1u8; 32].into()],
+                agreement_file: StorageInfo {
+                    hash: [7u8; 32].into(),
+                    hash_algo: HashAlgorithm::Sha2x256,
+                    creator: [1u8; 32].into(),
+                    usage: "doc".to_string(),
+                    save_at: StorageBackend::Ipfs,
+                    url: "https://ipfs.io/ipfs/QmNp5n7FFav5ZDaHAj6HzuhJ8LDbL1N6NRzAgT6piWS2Kx".to_string()
+                },
+                ..Default::default()
+            };
+            let index = polkasion.create_agreement(params.clone());
+            assert_eq!(polkasion.index(), index + 1);
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.name, params.name);
+            assert_eq!(res.signers, params.signers);
+        }
+
+        #[ink::test]
+        fn create_agreement_and_query_agreement_by_creator() {
+            let test_account :AccountId = [0u8; 32].into();
+            let mut polkasion = Polkasign::new(test_account);
+            let params = CreateAgreementParams {
+                name: "test".to_string(),
+                signers: vec![[1u8; 32].into()],
+                agreement_file: StorageInfo {
+                    hash: [7u8; 32].into(),
+                    hash_algo: HashAlgorithm::Sha2x256,
+                    creator: [1u8; 32].into(),
+                    usage: "doc".to_string(),
+                    save_at: StorageBackend::Ipfs,
+                    url: "https://ipfs.io/ipfs/QmNp5n7FFav5ZDaHAj6HzuhJ8LDbL1N6NRzAgT6piWS2Kx".to_string()
+                },
+                ..Default::default()
+            };
+            let index = polkasion.create_agreement(params.clone());
+            assert_eq!(polkasion.index(), index + 1);
+            let res = polkasion.query_agreement_by_creator([1u8; 32].into(), PageParams{
+                page_index: 0,
+                page_size: 10,
+            });
+            assert_eq!(res.total, 1);
+            assert_eq!(res.data[0].name, params.name);
+            assert_eq!(res.data[0].signers, params.signers);
+        }
+
+        #[ink::test]
+        fn create_agreement_and_query_agreement_by_collaborator() {
+            let test_account :AccountId = [0u8; 32].into();
+            let mut polkasion = Polkasign::new(test_account);
+            let params = CreateAgreementParams {
+                name: "test".to_string(),
+                signers: vec![[1u8; 32].into(), [2u8; 32].into()],
+                agreement_file: StorageInfo {
+                    hash: [7u8; 32].into(),
+                    hash_algo: HashAlgorithm::Sha2x256,
+                    creator: [1u8; 32].into(),
+                    usage: "doc".to_string(),
+                    save_at: StorageBackend::Ipfs,
+                    url: "https://ipfs.io/ipfs/QmNp5n7FFav5ZDaHAj6HzuhJ8LDbL1N6NRzAgT6piWS2Kx".to_string()
+                },
+                ..Default::default()
+            };
+            let index = polkasion.create_agreement(params.clone());
+            assert_eq!(polkasion.index(), index + 1);
+            let res = polkasion.query_agreement_by_collaborator([2u8; 32].into(), PageParams{
+                page_index: 0,
+                page_size: 10,
+            });
+            assert_eq!(res.total, 1);
+            assert_eq!(res.data[0].name, params.name);
+            assert_eq!(res.data[0].signers, params.signers);
+        }
+
+        #[ink::test]
+        fn attach_resource_and_query_agreement_by_id() {
+            let test_account :AccountId = [0u8; 32].into();
+            let mut polkasion = Polkasign::new(test_account);
+            let params = CreateAgreementParams {
+                name: "test".to_string(),
+                signers: vec![[1u8; 32].into()],
+                agreement_file: StorageInfo {
+                    hash: [7u8; 32].into(),
+                    hash_algo: HashAlgorithm::Sha2x256,
+                    creator: [1u8; 32].into(),
+                    usage: "doc".to_string(),
+                    save_at: StorageBackend::Ipfs,
+                    url: "https://ipfs.io/ipfs/QmNp5n7FFav5ZDaHAj6HzuhJ8LDbL1N6NRzAgT6piWS2Kx".to_string()
+                },
+                ..Default::default()
+            };
+            let index = polkasion.create_agreement(params.clone());
+            assert_eq!(polkasion.index(), index + 1);
+
+            // attach resource
+            let info = StorageInfo {
+                hash: [2u8; 32].into(),
+                hash_algo: HashAlgorithm::Sha2x256,
+                creator: [1u8; 32].into(),
+                usage: "comment".to_string(),
+                save_at: StorageBackend::Ipfs,
+                url: "https://ipfs.io/ipfs/QmNUVJPjvXxb55spvBuoNKEvGWzoGzzmwLC8MAovVWhMiR".to_string()
+            };
+            polkasion.attach_resource_to_agreement(index, info.clone());
+
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.name, params.name);
+            assert_eq!(res.signers, params.signers);
+            assert_eq!(res.resources[0].hash, info.hash);
+        }
+
+        fn set_caller(caller: AccountId) {
+            set_caller_with_value(caller, 0);
+        }
+
+        fn set_caller_with_value(caller: AccountId, value: Balance) {
+            let callee = ink_env::account_id::<ink_env::DefaultEnvironment>()
+                .unwrap_or([0x0; 32].into());
+            ink_env::test::push_execution_context::<ink_env::DefaultEnvironment>(
+                caller,
+                callee,
+                1000000,
+                value,
+                ink_env::test::CallData::new(ink_env::call::Selector::new([0x00; 4])),
+            );
+        }
+
+        fn contract_id() -> AccountId {
+            ink_env::account_id::<ink_env::DefaultEnvironment>().unwrap()
+        }
+
+        fn balance_of(account: AccountId) -> Balance {
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(account).unwrap()
+        }
+
+        fn test_params(signers: Vec<AccountId>) -> CreateAgreementParams {
+            CreateAgreementParams {
+                name: "test".to_string(),
+                signers,
+                agreement_file: StorageInfo {
+                    hash: [7u8; 32].into(),
+                    hash_algo: HashAlgorithm::Sha2x256,
+                    creator: [1u8; 32].into(),
+                    usage: "doc".to_string(),
+                    save_at: StorageBackend::Ipfs,
+                    url: "https://ipfs.io/ipfs/QmNp5n7FFav5ZDaHAj6HzuhJ8LDbL1N6NRzAgT6piWS2Kx".to_string()
+                },
+                ..Default::default()
+            }
+        }
+
+        #[ink::test]
+        fn sign_finishes_agreement() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            assert_eq!(polkasion.query_agreement_by_id(index).status, 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 2);
+            assert_eq!(polkasion.query_agreement_by_id(index).status, 2);
+        }
+
+        #[ink::test]
+        fn notary_attestation_required_to_finish() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            polkasion.add_notary(accounts.charlie);
+            assert!(polkasion.is_notary(accounts.charlie));
+
+            let mut params = test_params(vec![accounts.alice]);
+            params.require_attestation = true;
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            assert_eq!(polkasion.query_agreement_by_id(index).status, 1);
+
+            set_caller(accounts.charlie);
+            polkasion.attest_signature(index, accounts.alice, StorageInfo::default());
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.status, 2);
+            assert_eq!(res.sign_infos[0].attestation.as_ref().unwrap().notary, accounts.charlie);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "not notary")]
+        fn attest_signature_by_unregistered_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion.attest_signature(index, accounts.alice, StorageInfo::default());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "notary is signer")]
+        fn attest_own_signature_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            polkasion.add_notary(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.require_attestation = true;
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion.attest_signature(index, accounts.alice, StorageInfo::default());
+        }
+
+        #[ink::test]
+        fn mandatory_clauses_required_to_finish() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.clauses = vec![
+                ClauseInfo { id: 1, title: "dpa".to_string(), content_hash: [3u8; 32].into(), mandatory: true },
+                ClauseInfo { id: 2, title: "marketing".to_string(), content_hash: [4u8; 32].into(), mandatory: false },
+            ];
+            let index = polkasion.create_agreement(params);
+
+            polkasion._set_clause_decisions(index, accounts.alice, vec![
+                ClauseDecision { clause_id: 1, accept: true },
+                ClauseDecision { clause_id: 2, accept: false },
+            ]);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 2);
+            assert_eq!(polkasion.query_agreement_by_id(index).status, 1);
+
+            polkasion._set_clause_decisions(index, accounts.bob, vec![ClauseDecision { clause_id: 1, accept: true }]);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 3);
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.status, 2);
+            assert_eq!(res.clauses[0].accepted_by, vec![accounts.alice, accounts.bob]);
+            assert_eq!(res.clauses[1].rejected_by, vec![accounts.alice]);
+        }
+
+        #[ink::test]
+        fn documents_signed_over_merkle_root() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            let proof = polkasion.query_document_proof(index, 0);
+            assert_eq!(proof.root, polkasion.query_agreement_by_id(index).sign_hash);
+            assert!(polkasion.verify_document_proof(proof));
+
+            let mut params = test_params(vec![accounts.alice]);
+            for i in 1..5u8 {
+                params.documents.push(StorageInfo { hash: [i; 32].into(), ..Default::default() });
+            }
+            let index = polkasion.create_agreement(params);
+            let root = polkasion.query_agreement_by_id(index).sign_hash;
+            for doc_index in 0..5 {
+                let proof = polkasion.query_document_proof(index, doc_index);
+                assert_eq!(proof.root, root);
+                assert!(polkasion.verify_document_proof(proof));
+            }
+
+            let mut proof = polkasion.query_document_proof(index, 3);
+            proof.document.hash = [9u8; 32].into();
+            assert!(!polkasion.verify_document_proof(proof));
+        }
+
+        #[ink::test]
+        fn finished_agreement_sealed_with_certificate() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            assert!(polkasion.query_agreement_by_id(index).seal_hash.is_none());
+
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            let res = polkasion.query_agreement_by_id(index);
+            let cert = polkasion.get_certificate(index);
+            assert_eq!(Some(cert.seal_hash), res.seal_hash);
+            assert_eq!(cert.sign_infos.len(), 1);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement closed")]
+        fn sign_after_finished_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.alice, [1u8; 64], 2);
+        }
+
+        #[ink::test]
+        fn notarize_and_lookup() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let hash: Hash = [5u8; 32].into();
+            assert!(polkasion.lookup_notarization(hash).is_none());
+
+            polkasion.notarize(hash, StorageInfo { hash, ..Default::default() });
+            polkasion._add_cosign(hash, accounts.bob, [0u8; 64], 1);
+            let res = polkasion.lookup_notarization(hash).unwrap();
+            assert_eq!(res.submitter, accounts.alice);
+            assert_eq!(res.cosigns[0].addr, accounts.bob);
+        }
+
+        #[ink::test]
+        fn verify_document_by_hash() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let first = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            let second = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion.attach_resource_to_agreement(second, StorageInfo { hash: [2u8; 32].into(), ..Default::default() });
+            polkasion._add_sign(first, accounts.alice, [0u8; 64], 1);
+
+            let res = polkasion.verify_document([7u8; 32].into());
+            assert_eq!(res.len(), 2);
+            assert_eq!(res[0].status, 2);
+            assert_eq!(res[0].sign_infos[0].create_at, 1);
+            assert_eq!(res[1].signers, vec![accounts.alice, accounts.bob]);
+            assert_eq!(polkasion.verify_document([2u8; 32].into())[0].index, second);
+            assert!(polkasion.verify_document([9u8; 32].into()).is_empty());
+        }
+
+        #[ink::test]
+        fn query_resources_by_attacher() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion.attach_resource_to_agreement(index, StorageInfo { hash: [1u8; 32].into(), ..Default::default() });
+            set_caller(accounts.bob);
+            polkasion.attach_resource_to_agreement(index, StorageInfo { hash: [2u8; 32].into(), ..Default::default() });
+            set_caller(accounts.alice);
+            polkasion.attach_resource_to_agreement(index, StorageInfo { hash: [3u8; 32].into(), ..Default::default() });
+
+            let res = polkasion.query_resources(index, None, false, PageParams { page_index: 0, page_size: 2 });
+            assert_eq!(res.total, 3);
+            assert_eq!(res.pages, 2);
+            assert_eq!(res.data[1].attacher, accounts.bob);
+            assert_eq!(res.data[1].info.hash, [2u8; 32].into());
+
+            let res = polkasion.query_resources(index, Some(accounts.alice), false, PageParams { page_index: 1, page_size: 1 });
+            assert_eq!(res.total, 2);
+            assert_eq!(res.data[0].info.hash, [3u8; 32].into());
+        }
+
+        #[ink::test]
+        fn retract_resource_leaves_tombstone() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion.attach_resource_to_agreement(index, StorageInfo { hash: [1u8; 32].into(), ..Default::default() });
+            polkasion.attach_resource_to_agreement(index, StorageInfo { hash: [2u8; 32].into(), ..Default::default() });
+            polkasion.retract_resource(index, 0, "wrong file".to_string());
+
+            let page = PageParams { page_index: 0, page_size: 10 };
+            let res = polkasion.query_resources(index, None, false, page.clone());
+            assert_eq!(res.total, 1);
+            assert_eq!(res.data[0].id, 1);
+            let res = polkasion.query_resources(index, None, true, page);
+            assert_eq!(res.total, 2);
+            assert_eq!(res.data[0].retraction.as_ref().unwrap().reason, "wrong file");
+            assert_eq!(polkasion.query_agreement_by_id(index).resources.len(), 1);
+            assert!(polkasion.verify_document([1u8; 32].into()).is_empty());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "not attacher")]
+        fn retract_resource_of_others_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion.attach_resource_to_agreement(index, StorageInfo { hash: [1u8; 32].into(), ..Default::default() });
+            set_caller(accounts.bob);
+            polkasion.retract_resource(index, 0, "wrong file".to_string());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "reason too long")]
+        fn retract_resource_long_reason_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion.attach_resource_to_agreement(index, StorageInfo { hash: [1u8; 32].into(), ..Default::default() });
+            polkasion.retract_resource(index, 0, "x".repeat(Polkasign::MAX_REASON_LEN + 1));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "url too long")]
+        fn post_comment_long_url_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            let url = "x".repeat(Polkasign::MAX_URL_LEN + 1);
+            polkasion.post_comment(index, None, StorageInfo { url, ..Default::default() });
+        }
+
+        #[ink::test]
+        fn post_comment_and_query_thread() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.bob]));
+            let first = polkasion.post_comment(index, None, StorageInfo::default());
+            set_caller(accounts.bob);
+            let second = polkasion.post_comment(index, None, StorageInfo::default());
+            let reply = polkasion.post_comment(index, Some(first), StorageInfo::default());
+            polkasion.post_comment(index, Some(reply), StorageInfo::default());
+            polkasion.post_comment(index, Some(second), StorageInfo::default());
+
+            let res = polkasion.query_comments(index, PageParams { page_index: 0, page_size: 10 });
+            assert_eq!(res.total, 5);
+            assert_eq!(res.data[1].author, accounts.bob);
+
+            let thread: Vec<u64> = polkasion.query_comment_thread(index, first).iter().map(|c| c.id).collect();
+            assert_eq!(thread, vec![0, 2, 3]);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "not party of agreement")]
+        fn post_comment_by_outsider_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.bob]));
+            set_caller(accounts.eve);
+            polkasion.post_comment(index, None, StorageInfo::default());
+        }
+
+        #[ink::test]
+        fn validate_ipfs_storage_info() {
+            let hash: Hash = [7u8; 32].into();
+            for (url, hash_algo) in [
+                ("QmNp5n7FFav5ZDaHAj6HzuhJ8LDbL1N6NRzAgT6piWS2Kx", HashAlgorithm::Sha2x256),
+                ("ipfs://bafkreiaha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4", HashAlgorithm::Sha2x256),
+                ("/ipfs/bafk2bzaceadqobyha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4dqo", HashAlgorithm::Blake2x256),
+                ("https://ipfs.io/ipfs/QmNUVJPjvXxb55spvBuoNKEvGWzoGzzmwLC8MAovVWhMiR/doc.pdf", HashAlgorithm::Keccak256),
+                ("https://bafkreiaha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4.ipfs.dweb.link", HashAlgorithm::Sha2x256),
+                ("https://bafkreiaha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4.ipfs.dweb.link/?filename=x.pdf", HashAlgorithm::Sha2x256),
+                ("https://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi.ipfs.dweb.link/doc.pdf", HashAlgorithm::Keccak256),
+                ("ipfs://bafkreiaha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4?filename=x.pdf", HashAlgorithm::Sha2x256),
+                ("https://ipfs.io/ipfs/QmNp5n7FFav5ZDaHAj6HzuhJ8LDbL1N6NRzAgT6piWS2Kx#page=2", HashAlgorithm::Sha2x256),
+            ] {
+                Polkasign::_validate_storage_info(&StorageInfo {
+                    hash,
+                    hash_algo,
+                    save_at: StorageBackend::Ipfs,
+                    url: url.to_string(),
+                    ..Default::default()
+                });
+            }
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "cid hash mismatch")]
+        fn validate_ipfs_storage_info_hash_mismatch() {
+            Polkasign::_validate_storage_info(&StorageInfo {
+                hash: [7u8; 32].into(),
+                save_at: StorageBackend::Ipfs,
+                url: "ipfs://QmNUVJPjvXxb55spvBuoNKEvGWzoGzzmwLC8MAovVWhMiR".to_string(),
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "cid hash mismatch")]
+        fn validate_subdomain_gateway_hash_mismatch() {
+            Polkasign::_validate_storage_info(&StorageInfo {
+                hash: [8u8; 32].into(),
+                save_at: StorageBackend::Ipfs,
+                url: "https://bafkreiaha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4dqobyha4.ipfs.dweb.link?filename=x.pdf".to_string(),
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid cid")]
+        fn create_agreement_with_malformed_cid_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.agreement_file.url = "http://ipfs.io/xxxx".to_string();
+            polkasion.create_agreement(params);
+        }
+
+        #[ink::test]
+        fn key_envelopes_scoped_to_participant() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.bob, accounts.charlie]));
+            polkasion.set_key_envelope(index, KeyEnvelopeParams { participant: accounts.bob, scheme: 1, ciphertext: vec![1, 2] });
+            polkasion.set_key_envelope(index, KeyEnvelopeParams { participant: accounts.charlie, scheme: 1, ciphertext: vec![3, 4] });
+            assert!(polkasion.query_key_envelope(index).is_none());
+
+            set_caller(accounts.bob);
+            assert_eq!(polkasion.query_key_envelope(index).unwrap().ciphertext, vec![1, 2]);
+
+            set_caller(accounts.alice);
+            polkasion.rotate_key_envelopes(index, vec![
+                KeyEnvelopeParams { participant: accounts.charlie, scheme: 2, ciphertext: vec![5] },
+            ]);
+            set_caller(accounts.bob);
+            assert!(polkasion.query_key_envelope(index).is_none());
+            set_caller(accounts.charlie);
+            let envelope = polkasion.query_key_envelope(index).unwrap();
+            assert_eq!(envelope.key_version, 1);
+            assert_eq!(envelope.ciphertext, vec![5]);
+        }
+
+        #[ink::test]
+        fn reveal_url_after_finished() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let url = test_params(vec![]).agreement_file.url;
+            let salt = vec![9u8; 16];
+            let mut params = test_params(vec![accounts.alice]);
+            params.agreement_file.url = String::new();
+            params.url_commitment = Some(polkasion.url_commitment(url.clone(), salt.clone()));
+            let index = polkasion.create_agreement(params);
+            assert!(polkasion.query_agreement_by_id(index).agreement_file.url.is_empty());
+
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion.reveal_url(index, url.clone(), salt);
+            assert_eq!(polkasion.query_agreement_by_id(index).agreement_file.url, url);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "wrong url or salt")]
+        fn reveal_url_with_wrong_salt_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let url = test_params(vec![]).agreement_file.url;
+            let mut params = test_params(vec![accounts.alice]);
+            params.agreement_file.url = String::new();
+            params.url_commitment = Some(polkasion.url_commitment(url.clone(), vec![9u8; 16]));
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion.reveal_url(index, url, vec![8u8; 16]);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "hash algorithm mismatch")]
+        fn validate_ipfs_storage_info_algo_mismatch() {
+            Polkasign::_validate_storage_info(&StorageInfo {
+                hash: [7u8; 32].into(),
+                hash_algo: HashAlgorithm::Keccak256,
+                save_at: StorageBackend::Ipfs,
+                url: "ipfs://QmNp5n7FFav5ZDaHAj6HzuhJ8LDbL1N6NRzAgT6piWS2Kx".to_string(),
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn compute_hash_of_inline_payload() {
+            let polkasion = Polkasign::new([0u8; 32].into());
+            let data = b"hello".to_vec();
+            // sha256("hello")
+            let expected: [u8; 32] = [
+                0x2c, 0xf2, 0x4d, 0xba, 0x5f, 0xb0, 0xa3, 0x0e, 0x26, 0xe8, 0x3b, 0x2a, 0xc5, 0xb9, 0xe2, 0x9e,
+                0x1b, 0x16, 0x1e, 0x5c, 0x1f, 0xa7, 0x42, 0x5e, 0x73, 0x04, 0x33, 0x62, 0x93, 0x8b, 0x98, 0x24,
+            ];
+            assert_eq!(polkasion.compute_hash(HashAlgorithm::Sha2x256, data.clone()), expected.into());
+            assert_ne!(polkasion.compute_hash(HashAlgorithm::Blake2x256, data.clone()), expected.into());
+            assert_ne!(polkasion.compute_hash(HashAlgorithm::Keccak256, data), expected.into());
+        }
+
+        #[ink::test]
+        fn query_my_agreements_by_labels() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.bob]);
+            params.tags = vec!["nda".to_string()];
+            let nda = polkasion.create_agreement(params);
+            let mut params = test_params(vec![accounts.bob]);
+            params.tags = vec!["vendor".to_string()];
+            params.metadata.insert("ref".to_string(), "PO-1".to_string());
+            let vendor = polkasion.create_agreement(params);
+            set_caller(accounts.bob);
+            polkasion.create_agreement(test_params(vec![accounts.charlie]));
+
+            let page = PageParams { page_index: 0, page_size: 10 };
+            let res = polkasion.query_my_agreements_by_tag("nda".to_string(), page.clone());
+            assert_eq!(res.total, 1);
+            assert_eq!(res.data[0].index, nda);
+            let res = polkasion.query_my_agreements_by_metadata_key("ref".to_string(), page.clone());
+            assert_eq!(res.data[0].index, vendor);
+
+            set_caller(accounts.alice);
+            polkasion.update_agreement_labels(vendor, vec!["nda".to_string()], BTreeMap::new());
+            assert_eq!(polkasion.query_my_agreements_by_tag("nda".to_string(), page.clone()).total, 2);
+            assert_eq!(polkasion.query_my_agreements_by_metadata_key("ref".to_string(), page).total, 0);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement not draft")]
+        fn update_labels_after_sign_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion.update_agreement_labels(index, vec!["nda".to_string()], BTreeMap::new());
+        }
+
+        #[ink::test]
+        fn superseding_agreement_updates_parent() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let parent = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion._add_sign(parent, accounts.alice, [0u8; 64], 1);
+
+            let mut params = test_params(vec![accounts.alice]);
+            params.parent = Some(AgreementLink { index: parent, link_type: LinkType::Addendum });
+            let addendum = polkasion.create_agreement(params);
+            let mut params = test_params(vec![accounts.alice]);
+            params.parent = Some(AgreementLink { index: parent, link_type: LinkType::Supersedes });
+            let renewal = polkasion.create_agreement(params);
+            assert_eq!(polkasion.query_agreement_by_id(parent).children, vec![addendum, renewal]);
+
+            polkasion._add_sign(addendum, accounts.alice, [0u8; 64], 2);
+            assert_eq!(polkasion.query_agreement_by_id(parent).status, STATUS_FINISHED);
+            polkasion._add_sign(renewal, accounts.alice, [0u8; 64], 3);
+            assert_eq!(polkasion.query_agreement_by_id(parent).status, STATUS_SUPERSEDED);
+        }
+
+        #[ink::test]
+        fn lifecycle_status_by_term() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.effective_from = Some(100);
+            params.terminates_at = Some(200);
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+
+            let a = polkasion.agreements_map.get(&index).unwrap();
+            assert_eq!(Polkasign::_lifecycle_status(a, 50), LifecycleStatus::Pending);
+            assert_eq!(Polkasign::_lifecycle_status(a, 100), LifecycleStatus::InEffect);
+            assert_eq!(Polkasign::_lifecycle_status(a, 200), LifecycleStatus::Expired);
+        }
+
+        #[ink::test]
+        fn terminate_early_by_all_signers() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 1);
+            assert_eq!(polkasion.query_lifecycle_status(index), LifecycleStatus::InEffect);
+
+            polkasion._add_termination_sign(index, accounts.alice, [0u8; 64], 2);
+            assert_eq!(polkasion.query_lifecycle_status(index), LifecycleStatus::InEffect);
+            polkasion._add_termination_sign(index, accounts.bob, [0u8; 64], 3);
+            assert_eq!(polkasion.query_lifecycle_status(index), LifecycleStatus::TerminatedEarly);
+            assert_eq!(polkasion.query_agreement_by_id(index).terminate_at, 3);
+        }
+
+        #[ink::test]
+        fn dispute_resolved_by_arbiter() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.arbiter = Some(accounts.eve);
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+
+            set_caller(accounts.bob);
+            polkasion.raise_dispute(index, StorageInfo::default());
+            assert_eq!(polkasion.query_lifecycle_status(index), LifecycleStatus::Disputed);
+
+            set_caller(accounts.eve);
+            polkasion.resolve_dispute(index, DisputeOutcome::Upheld, StorageInfo::default());
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.status, STATUS_WAITING);
+            assert_eq!(res.disputes[0].outcome, Some(DisputeOutcome::Upheld));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement disputed")]
+        fn attach_resource_while_disputed_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.arbiter = Some(accounts.eve);
+            let index = polkasion.create_agreement(params);
+            polkasion.raise_dispute(index, StorageInfo::default());
+            polkasion.attach_resource_to_agreement(index, StorageInfo::default());
+        }
+
+        #[ink::test]
+        fn escrow_released_when_finished() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.beneficiaries = vec![
+                Beneficiary { account: accounts.charlie, share: 1 },
+                Beneficiary { account: accounts.django, share: 2 },
+            ];
+            let index = polkasion.create_agreement(params);
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 100).unwrap();
+            set_caller_with_value(accounts.alice, 100);
+            polkasion.deposit_escrow(index);
+            assert_eq!(polkasion.query_agreement_by_id(index).escrow.balance, 100);
+
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 2);
+            assert!(polkasion.query_agreement_by_id(index).escrow.released);
+            assert_eq!(balance_of(accounts.charlie), 33);
+            assert_eq!(balance_of(accounts.django), 67);
+        }
+
+        #[ink::test]
+        fn escrow_reclaimed_when_cancelled() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.beneficiaries = vec![Beneficiary { account: accounts.charlie, share: 1 }];
+            let index = polkasion.create_agreement(params);
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 40).unwrap();
+            set_caller_with_value(accounts.bob, 40);
+            polkasion.deposit_escrow(index);
+            let bob_balance = balance_of(accounts.bob);
+
+            set_caller(accounts.alice);
+            polkasion.cancel_agreement(index);
+            set_caller(accounts.bob);
+            polkasion.reclaim_escrow(index);
+            assert_eq!(balance_of(accounts.bob), bob_balance + 40);
+            assert_eq!(polkasion.query_agreement_by_id(index).escrow.balance, 0);
+        }
+
+        #[ink::test]
+        fn milestone_delivered_and_approved() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.milestones = vec![MilestoneParams {
+                token: accounts.frank,
+                payer: accounts.alice,
+                payee: accounts.bob,
+                amount: 10,
+            }];
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 2);
+
+            polkasion._deliver_milestone(index, 0, accounts.bob, [0u8; 64], 3);
+            let m = polkasion._approve_milestone(index, 0, accounts.alice, [0u8; 64], 4);
+            assert_eq!((m.token, m.payer, m.payee, m.amount), (accounts.frank, accounts.alice, accounts.bob, 10));
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.milestones[0].delivered.as_ref().unwrap().create_at, 3);
+            assert_eq!(res.milestones[0].approved.as_ref().unwrap().addr, accounts.alice);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "milestone not delivered")]
+        fn approve_undelivered_milestone_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.milestones = vec![MilestoneParams {
+                token: accounts.frank,
+                payer: accounts.alice,
+                payee: accounts.bob,
+                amount: 10,
+            }];
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 2);
+            polkasion._approve_milestone(index, 0, accounts.alice, [0u8; 64], 3);
+        }
+
+        #[ink::test]
+        fn certificates_minted_when_finished() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            assert_eq!(polkasion.query_certificates_of(accounts.bob, PageParams { page_index: 0, page_size: 10 }).total, 0);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 2);
+
+            let res = polkasion.query_certificates_of(accounts.bob, PageParams { page_index: 0, page_size: 10 });
+            assert_eq!(res.total, 1);
+            assert_eq!(res.data[0].index, index);
+            assert_eq!(Some(res.data[0].seal_hash), polkasion.query_agreement_by_id(index).seal_hash);
+            assert_eq!(polkasion.query_certificate_token(0).unwrap().owner, accounts.alice);
+            assert!(polkasion.query_certificate_token(2).is_none());
+        }
+
+        #[ink::test]
+        fn certificate_minted_to_creator_not_signing() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.bob, accounts.charlie]));
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.charlie, [0u8; 64], 2);
+
+            let owners: Vec<AccountId> = (0..3).map(|id| polkasion.query_certificate_token(id).unwrap().owner).collect();
+            assert_eq!(owners, vec![accounts.alice, accounts.bob, accounts.charlie]);
+            assert!(polkasion.query_certificate_token(3).is_none());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "certificate not transferable")]
+        fn transfer_certificate_fails_by_default() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion.transfer_certificate(0, accounts.bob);
+        }
+
+        #[ink::test]
+        fn callback_recorded_when_finished_and_cancelled() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let callback = CallbackInfo { callee: accounts.frank, selector: [1, 2, 3, 4], gas_limit: 100000 };
+            let mut params = test_params(vec![accounts.alice]);
+            params.callback = Some(callback.clone());
+            let finished = polkasion.create_agreement(params);
+            polkasion._add_sign(finished, accounts.alice, [0u8; 64], 1);
+            let res = polkasion.query_agreement_by_id(finished);
+            assert_eq!(res.callback_results.len(), 1);
+            assert_eq!(res.callback_results[0].status, STATUS_FINISHED);
+            assert!(res.callback_results[0].success);
+
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.callback = Some(callback);
+            let cancelled = polkasion.create_agreement(params);
+            polkasion.cancel_agreement(cancelled);
+            let res = polkasion.query_agreement_by_id(cancelled);
+            assert_eq!(res.callback_results.len(), 1);
+            assert_eq!(res.callback_results[0].status, STATUS_CANCELLED);
+
+            let calls = CALLBACK_CALLS.with(|calls| calls.borrow().clone());
+            assert_eq!(calls, vec![
+                (accounts.frank, [1, 2, 3, 4], finished, polkasion.query_agreement_by_id(finished).seal_hash),
+                (accounts.frank, [1, 2, 3, 4], cancelled, None),
+            ]);
+        }
+
+        #[ink::test]
+        fn callback_failure_recorded_without_reverting() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.callback = Some(CallbackInfo { callee: accounts.frank, selector: [1, 2, 3, 4], gas_limit: 100000 });
+            let index = polkasion.create_agreement(params);
+            CALLBACK_FAILS.with(|fails| fails.set(true));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.status, STATUS_FINISHED);
+            assert!(!res.callback_results[0].success);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "wrong callback gas limit")]
+        fn callback_unlimited_gas_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.callback = Some(CallbackInfo { callee: accounts.frank, selector: [1, 2, 3, 4], gas_limit: 0 });
+            polkasion.create_agreement(params);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "wrong callback gas limit")]
+        fn callback_gas_over_cap_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.callback = Some(CallbackInfo {
+                callee: accounts.frank,
+                selector: [1, 2, 3, 4],
+                gas_limit: Polkasign::MAX_CALLBACK_GAS + 1,
+            });
+            polkasion.create_agreement(params);
+        }
+
+        #[ink::test]
+        fn agreement_registry_trait_works() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = AgreementRegistry::create(&mut polkasion, test_params(vec![accounts.alice]));
+            assert_eq!(AgreementRegistry::status(&polkasion, index), LifecycleStatus::Pending);
+            assert_eq!(AgreementRegistry::verify_document(&polkasion, [7; 32].into())[0].index, index);
+
+            register_crypto_extension();
+            let sign_hash = polkasion.query_agreement_by_id(index).sign_hash;
+            AgreementRegistry::sign(&mut polkasion, index, test_sign(accounts.alice, sign_hash));
+            assert_eq!(AgreementRegistry::status(&polkasion, index), LifecycleStatus::InEffect);
+        }
+
+        #[ink::test]
+        fn create_fee_collected_and_withdrawn() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            polkasion.set_fees(10, 5);
+            assert_eq!(polkasion.fees(), (10, 5));
+
+            set_caller_with_value(accounts.bob, 20);
+            polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            assert_eq!(polkasion.treasury(), 20);
+
+            set_caller(accounts.alice);
+            polkasion.add_fee_exempt(accounts.charlie);
+            set_caller(accounts.charlie);
+            polkasion.create_agreement(test_params(vec![accounts.charlie]));
+            assert_eq!(polkasion.treasury(), 20);
+
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 20).unwrap();
+            set_caller(accounts.alice);
+            polkasion.withdraw(accounts.django, 15);
+            assert_eq!(polkasion.treasury(), 5);
+            assert_eq!(balance_of(accounts.django), 15);
+        }
+
+        #[ink::test]
+        fn overpaid_fee_refunded() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            polkasion.set_fees(10, 5);
+            set_caller_with_value(accounts.bob, 30);
+            polkasion.create_agreement(test_params(vec![accounts.bob]));
+            polkasion.create_agreement(test_params(vec![accounts.bob]));
+            assert_eq!(polkasion.treasury(), 30);
+            assert_eq!(polkasion.query_refund(accounts.bob), 30);
+
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 60).unwrap();
+            let bob_balance = balance_of(accounts.bob);
+            polkasion.withdraw_refund();
+            assert_eq!(balance_of(accounts.bob), bob_balance + 30);
+            assert_eq!(polkasion.query_refund(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "fee overflow")]
+        fn create_with_overflowing_fee_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            polkasion.set_fees(1, Balance::MAX / 2 + 1);
+            set_caller_with_value(accounts.bob, 10);
+            polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "deposit overflow")]
+        fn create_with_overflowing_deposit_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            polkasion.set_deposit_per_byte(Balance::MAX / 2);
+            polkasion.create_agreement(test_params(vec![accounts.alice]));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "insufficient fee")]
+        fn create_without_fee_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            polkasion.set_fees(10, 5);
+            set_caller_with_value(accounts.bob, 10);
+            polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+        }
+
+        #[ink::test]
+        fn archive_refunds_deposit_and_keeps_seal_hash() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            polkasion.set_deposit_per_byte(1);
+            set_caller_with_value(accounts.bob, 10000);
+            let index = polkasion.create_agreement(test_params(vec![accounts.bob]));
+            let deposit = polkasion.query_agreement_by_id(index).deposit;
+            assert!(deposit > 0);
+            assert_eq!(polkasion.treasury(), 0);
+            assert_eq!(polkasion.query_refund(accounts.bob), 10000 - deposit);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 1);
+            let seal_hash = polkasion.query_agreement_by_id(index).seal_hash;
+
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 10000).unwrap();
+            let bob_balance = balance_of(accounts.bob);
+            set_caller(accounts.bob);
+            polkasion.archive_agreement(index);
+            assert_eq!(balance_of(accounts.bob), bob_balance + deposit);
+            assert_eq!(polkasion.query_archived_seal_hash(index), seal_hash);
+            assert!(polkasion.verify_document([7; 32].into()).is_empty());
+            let page = PageParams { page_index: 0, page_size: 10 };
+            assert_eq!(polkasion.query_agreement_by_creator(accounts.bob, page).total, 0);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement not closed")]
+        fn archive_open_agreement_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion.archive_agreement(index);
+        }
+
+        #[ink::test]
+        fn ownership_transferred_when_accepted() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            polkasion.transfer_ownership(accounts.bob);
+            assert_eq!(polkasion.owner(), accounts.alice);
+            assert_eq!(polkasion.pending_owner(), Some(accounts.bob));
+
+            set_caller(accounts.bob);
+            polkasion.accept_ownership();
+            assert_eq!(polkasion.owner(), accounts.bob);
+            assert_eq!(polkasion.pending_owner(), None);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "contract paused")]
+        fn create_while_paused_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            polkasion.pause();
+            assert!(polkasion.paused());
+            polkasion.create_agreement(test_params(vec![accounts.alice]));
+        }
+
+        #[ink::test]
+        fn sign_after_unfrozen() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion.freeze_agreement(index);
+            assert!(polkasion.query_agreement_by_id(index).frozen);
+            polkasion.unfreeze_agreement(index);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            assert_eq!(polkasion.query_agreement_by_id(index).status, STATUS_FINISHED);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement frozen")]
+        fn sign_frozen_agreement_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion.freeze_agreement(index);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+        }
+
+        #[ink::test]
+        fn sign_with_clauses_verifies_sign() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.clauses = vec![ClauseInfo { id: 1, title: "dpa".to_string(), content_hash: [3u8; 32].into(), mandatory: true }];
+            let index = polkasion.create_agreement(params);
+            let decisions = vec![ClauseDecision { clause_id: 1, accept: true }];
+            let sign_hash = polkasion.clause_sign_hash(index, decisions.clone());
+            assert_ne!(sign_hash, polkasion.query_agreement_by_id(index).sign_hash);
+
+            for signer in [accounts.alice, accounts.bob] {
+                set_caller(signer);
+                polkasion.sign_with_clauses(index, decisions.clone(), test_sign(signer, sign_hash));
+            }
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.status, STATUS_FINISHED);
+            assert_eq!(res.clauses[0].accepted_by, vec![accounts.alice, accounts.bob]);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "wrong sign")]
+        fn sign_with_clauses_wrong_sign_fails() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            let sign_hash = polkasion.query_agreement_by_id(index).sign_hash;
+            set_caller(accounts.bob);
+            polkasion.sign_with_clauses(index, Vec::new(), test_sign(accounts.alice, sign_hash));
+        }
+
+        #[ink::test]
+        fn create_and_attach_with_sign() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let params = test_params(vec![accounts.alice, accounts.bob]);
+            let sign_hash = Polkasign::_document_root(&params.agreement_file, &params.documents);
+            set_caller(accounts.alice);
+            polkasion.create_agreement_with_sign(params, StorageInfo::default(), test_sign(accounts.alice, sign_hash));
+            assert_eq!(polkasion.query_agreement_by_id(0).status, STATUS_WAITING);
+
+            set_caller(accounts.bob);
+            polkasion.attach_resource_with_sign(0, StorageInfo::default(), test_sign(accounts.bob, sign_hash));
+            let res = polkasion.query_agreement_by_id(0);
+            assert_eq!(res.status, STATUS_FINISHED);
+            assert_eq!(res.resources.len(), 2);
+        }
+
+        #[ink::test]
+        fn terminate_early_verifies_sign() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 1);
+
+            let msg = polkasion.termination_hash(index);
+            for signer in [accounts.alice, accounts.bob] {
+                set_caller(signer);
+                polkasion.terminate_early(index, test_sign(signer, msg));
+            }
+            assert_eq!(polkasion.query_lifecycle_status(index), LifecycleStatus::TerminatedEarly);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "wrong sign")]
+        fn cosign_notarization_wrong_sign_fails() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let hash: Hash = [7u8; 32].into();
+            polkasion.notarize(hash, test_params(Vec::new()).agreement_file);
+            set_caller(accounts.bob);
+            polkasion.cosign_notarization(hash, test_sign(accounts.bob, hash));
+            assert_eq!(polkasion.lookup_notarization(hash).unwrap().cosigns.len(), 1);
+            polkasion.cosign_notarization(hash, test_sign(accounts.bob, [8u8; 32].into()));
+        }
+
+        #[ink::test]
+        fn milestone_paid_through_messages() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.milestones = vec![MilestoneParams {
+                token: accounts.frank,
+                payer: accounts.alice,
+                payee: accounts.bob,
+                amount: 10,
+            }];
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 2);
+
+            let msg = polkasion.milestone_hash(index, 0);
+            set_caller(accounts.bob);
+            polkasion.deliver_milestone(index, 0, test_sign(accounts.bob, msg));
+            set_caller(accounts.alice);
+            polkasion.approve_milestone(index, 0, test_sign(accounts.alice, msg));
+            let transfers = TOKEN_TRANSFERS.with(|transfers| transfers.borrow().clone());
+            assert_eq!(transfers, vec![(accounts.frank, accounts.alice, accounts.bob, 10)]);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "wrong sign")]
+        fn deliver_milestone_wrong_sign_fails() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.milestones = vec![MilestoneParams {
+                token: accounts.frank,
+                payer: accounts.alice,
+                payee: accounts.bob,
+                amount: 10,
+            }];
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(index, accounts.bob, [0u8; 64], 2);
+            set_caller(accounts.bob);
+            polkasion.deliver_milestone(index, 0, [0u8; 64]);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "not found in signers")]
+        fn agreement_registry_sign_by_outsider_fails() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = AgreementRegistry::create(&mut polkasion, test_params(vec![accounts.alice, accounts.bob]));
+            let sign_hash = polkasion.query_agreement_by_id(index).sign_hash;
+            set_caller(accounts.eve);
+            AgreementRegistry::sign(&mut polkasion, index, test_sign(accounts.eve, sign_hash));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "parent signers missing")]
+        fn supersede_without_parent_signers_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let parent = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion._add_sign(parent, accounts.alice, [0u8; 64], 1);
+            polkasion._add_sign(parent, accounts.bob, [0u8; 64], 1);
+
+            let mut params = test_params(vec![accounts.alice]);
+            params.parent = Some(AgreementLink { index: parent, link_type: LinkType::Terminates });
+            polkasion.create_agreement(params);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "escrow not empty")]
+        fn set_beneficiaries_after_deposit_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.beneficiaries = vec![Beneficiary { account: accounts.charlie, share: 1 }];
+            let index = polkasion.create_agreement(params);
+            set_caller_with_value(accounts.alice, 10);
+            polkasion.deposit_escrow(index);
+            set_caller(accounts.alice);
+            polkasion.set_escrow_beneficiaries(index, Vec::new());
+        }
+
+        #[ink::test]
+        fn failed_escrow_payout_withdrawn_later() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.beneficiaries = vec![
+                Beneficiary { account: accounts.charlie, share: 1 },
+                Beneficiary { account: accounts.django, share: 2 },
+            ];
+            let index = polkasion.create_agreement(params);
+            set_caller_with_value(accounts.alice, 100);
+            polkasion.deposit_escrow(index);
+
+            // contract can't cover the second payout
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 50).unwrap();
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            let res = polkasion.query_agreement_by_id(index);
+            assert_eq!(res.status, STATUS_FINISHED);
+            assert_eq!(balance_of(accounts.charlie), 33);
+            assert_eq!(res.escrow.unpaid.get(&accounts.django), Some(&67));
+
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(contract_id(), 67).unwrap();
+            set_caller(accounts.django);
+            polkasion.withdraw_escrow_payout(index);
+            assert_eq!(balance_of(accounts.django), 67);
+            assert!(polkasion.query_agreement_by_id(index).escrow.unpaid.is_empty());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement frozen")]
+        fn retract_resource_frozen_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion.attach_resource_to_agreement(index, StorageInfo::default());
+            polkasion.freeze_agreement(index);
+            polkasion.retract_resource(index, 0, "typo".to_string());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement frozen")]
+        fn raise_dispute_frozen_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.arbiter = Some(accounts.eve);
+            let index = polkasion.create_agreement(params);
+            polkasion.freeze_agreement(index);
+            polkasion.raise_dispute(index, StorageInfo::default());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement frozen")]
+        fn post_comment_frozen_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion.freeze_agreement(index);
+            polkasion.post_comment(index, None, StorageInfo::default());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement frozen")]
+        fn update_labels_frozen_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            set_caller(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice, accounts.bob]));
+            polkasion.freeze_agreement(index);
+            polkasion.update_agreement_labels(index, vec!["nda".to_string()], BTreeMap::new());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement closed")]
+        fn attest_signature_after_seal_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            polkasion.add_notary(accounts.charlie);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            assert!(polkasion.query_agreement_by_id(index).seal_hash.is_some());
+
+            set_caller(accounts.charlie);
+            polkasion.attest_signature(index, accounts.alice, StorageInfo::default());
+        }
+
+        #[ink::test]
+        fn merkle_root_not_provable_as_document() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.documents.push(StorageInfo { hash: [1u8; 32].into(), ..Default::default() });
+            let index = polkasion.create_agreement(params);
+            let mut proof = polkasion.query_document_proof(index, 1);
+
+            // claim the root itself is the only document
+            proof.document.hash = proof.root;
+            proof.doc_index = 0;
+            proof.total = 1;
+            proof.proof = Vec::new();
+            assert!(!polkasion.verify_document_proof(proof.clone()));
+
+            // claim the sibling leaf hash is the document with a smaller tree
+            let mut proof = polkasion.query_document_proof(index, 1);
+            proof.document.hash = proof.proof[0];
+            proof.total = 1;
+            proof.doc_index = 0;
+            proof.proof = Vec::new();
+            assert!(!polkasion.verify_document_proof(proof));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "wrong sign")]
+        fn sign_with_clauses_over_plain_sign_hash_fails() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice, accounts.bob]);
+            params.clauses = vec![ClauseInfo { id: 1, title: "dpa".to_string(), content_hash: [3u8; 32].into(), mandatory: true }];
+            let index = polkasion.create_agreement(params);
+            let sign_hash = polkasion.query_agreement_by_id(index).sign_hash;
+            set_caller(accounts.bob);
+            polkasion.sign_with_clauses(index, vec![ClauseDecision { clause_id: 1, accept: false }], test_sign(accounts.bob, sign_hash));
+        }
+
+        #[ink::test]
+        fn clause_decisions_replaced_and_sealed() {
+            register_crypto_extension();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.clauses = vec![
+                ClauseInfo { id: 1, title: "dpa".to_string(), content_hash: [3u8; 32].into(), mandatory: false },
+                ClauseInfo { id: 2, title: "nda".to_string(), content_hash: [4u8; 32].into(), mandatory: false },
+            ];
+            let index = polkasion.create_agreement(params);
+            polkasion._set_clause_decisions(index, accounts.alice, vec![ClauseDecision { clause_id: 1, accept: true }]);
+            polkasion._set_clause_decisions(index, accounts.alice, vec![ClauseDecision { clause_id: 2, accept: true }]);
+            let res = polkasion.query_agreement_by_id(index);
+            assert!(res.clauses[0].accepted_by.is_empty());
+            assert_eq!(res.clauses[1].accepted_by, vec![accounts.alice]);
+
+            let a = polkasion.agreements_map.get(&index).unwrap().clone();
+            let mut b = a.clone();
+            b.clause_decisions.insert(accounts.alice, BTreeMap::new());
+            assert_ne!(Polkasign::_seal_hash(&a), Polkasign::_seal_hash(&b));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "too many resources")]
+        fn attach_resource_over_cap_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let index = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            for i in 0..=Polkasign::MAX_RESOURCES {
+                polkasion.attach_resource_to_agreement(index, StorageInfo { hash: [i as u8; 32].into(), ..Default::default() });
+            }
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "too many disputes")]
+        fn raise_dispute_over_cap_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.arbiter = Some(accounts.eve);
+            let index = polkasion.create_agreement(params);
+            for _ in 0..=Polkasign::MAX_DISPUTES {
+                set_caller(accounts.alice);
+                polkasion.raise_dispute(index, StorageInfo::default());
+                set_caller(accounts.eve);
+                polkasion.resolve_dispute(index, DisputeOutcome::Upheld, StorageInfo::default());
+            }
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "agreement frozen")]
+        fn link_to_frozen_parent_fails() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let parent = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion.freeze_agreement(parent);
+            let mut params = test_params(vec![accounts.alice]);
+            params.parent = Some(AgreementLink { index: parent, link_type: LinkType::Addendum });
+            polkasion.create_agreement(params);
+        }
+
+        #[ink::test]
+        fn frozen_parent_superseded_once_unfrozen() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let parent = polkasion.create_agreement(test_params(vec![accounts.alice]));
+            polkasion._add_sign(parent, accounts.alice, [0u8; 64], 1);
+            let mut params = test_params(vec![accounts.alice]);
+            params.parent = Some(AgreementLink { index: parent, link_type: LinkType::Supersedes });
+            let renewal = polkasion.create_agreement(params);
+
+            polkasion.freeze_agreement(parent);
+            polkasion._add_sign(renewal, accounts.alice, [0u8; 64], 2);
+            assert_eq!(polkasion.query_agreement_by_id(parent).status, STATUS_FINISHED);
+            polkasion.unfreeze_agreement(parent);
+            assert_eq!(polkasion.query_agreement_by_id(parent).status, STATUS_SUPERSEDED);
+        }
+
+        #[ink::test]
+        fn disputed_parent_superseded_once_upheld() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            let mut params = test_params(vec![accounts.alice]);
+            params.arbiter = Some(accounts.eve);
+            let parent = polkasion.create_agreement(params);
+            polkasion._add_sign(parent, accounts.alice, [0u8; 64], 1);
+            let mut params = test_params(vec![accounts.alice]);
+            params.parent = Some(AgreementLink { index: parent, link_type: LinkType::Supersedes });
+            let renewal = polkasion.create_agreement(params);
+
+            polkasion.raise_dispute(parent, StorageInfo::default());
+            polkasion._add_sign(renewal, accounts.alice, [0u8; 64], 2);
+            assert_eq!(polkasion.query_agreement_by_id(parent).status, STATUS_DISPUTED);
+            set_caller(accounts.eve);
+            polkasion.resolve_dispute(parent, DisputeOutcome::Upheld, StorageInfo::default());
+            assert_eq!(polkasion.query_agreement_by_id(parent).status, STATUS_SUPERSEDED);
+        }
+
+        #[ink::test]
+        fn seal_hash_covers_attestation() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+            let mut polkasion = Polkasign::new(accounts.alice);
+            polkasion.add_notary(accounts.charlie);
+            let mut params = test_params(vec![accounts.alice]);
+            params.require_attestation = true;
+            let index = polkasion.create_agreement(params);
+            polkasion._add_sign(index, accounts.alice, [0u8; 64], 1);
+            set_caller(accounts.charlie);
+            polkasion.attest_signature(index, accounts.alice, StorageInfo::default());
+            let a = polkasion.agreements_map.get(&index).unwrap().clone();
+            assert_eq!(a.seal_hash, Some(Polkasign::_seal_hash(&a)));
+
+            let mut b = a.clone();
+            b.sign_infos.get_mut(&accounts.alice).unwrap().attestation.as_mut().unwrap().notary = accounts.django;
+            assert_ne!(Polkasign::_seal_hash(&a), Polkasign::_seal_hash(&b));
+        }
+    }
+}
+
+/// Registry used only through the crate root exports, like a contract built with `ink-as-dependency`.
+#[cfg(test)]
+mod dependency_tests {
+    use ink_lang as ink;
+    use crate::{
+        AgreementRegistry, AgreementLink, Beneficiary, ClauseInfo, CreateAgreementParams, HashAlgorithm,
+        LifecycleStatus, LinkType, Polkasign, StorageBackend, StorageInfo,
+    };
+
+    fn storage_info(hash: [u8; 32]) -> StorageInfo {
+        StorageInfo {
+            hash: hash.into(),
+            hash_algo: HashAlgorithm::Sha2x256,
+            creator: [1u8; 32].into(),
+            usage: "doc".into(),
+            save_at: StorageBackend::Other,
+            url: "https://example.com/doc.pdf".into(),
+        }
+    }
+
+    #[ink::test]
+    fn params_built_and_verify_info_read_through_exports() {
+        let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>().unwrap();
+        let mut polkasign = Polkasign::new(accounts.alice);
+        let parent = AgreementRegistry::create(&mut polkasign, CreateAgreementParams {
+            name: "msa".into(),
+            signers: vec![accounts.alice, accounts.bob],
+            agreement_file: storage_info([1u8; 32]),
+            ..Default::default()
+        });
+        let index = AgreementRegistry::create(&mut polkasign, CreateAgreementParams {
+            name: "addendum".into(),
+            signers: vec![accounts.alice, accounts.bob],
+            agreement_file: storage_info([2u8; 32]),
+            documents: vec![storage_info([3u8; 32])],
+            clauses: vec![ClauseInfo { id: 1, title: "dpa".into(), content_hash: [4u8; 32].into(), mandatory: false }],
+            tags: vec!["nda".into()],
+            parent: Some(AgreementLink { index: parent, link_type: LinkType::Addendum }),
+            beneficiaries: vec![Beneficiary { account: accounts.charlie, share: 1 }],
+            ..Default::default()
+        });
+
+        let infos = AgreementRegistry::verify_document(&polkasign, [3u8; 32].into());
+        assert_eq!(infos.len(), 1);
+        let info = &infos[0];
+        assert_eq!(info.index, index);
+        assert_eq!(info.name, "addendum");
+        assert_eq!(info.creator, accounts.alice);
+        assert_eq!(info.signers, vec![accounts.alice, accounts.bob]);
+        assert!(info.sign_infos.is_empty());
+        assert_eq!(info.seal_hash, None);
+        assert_eq!(info.lifecycle, LifecycleStatus::Pending);
     }
 }
@@ -0,0 +1,101 @@
+use ink_env::hash::{Blake2x256, HashOutput};
+use ink_prelude::vec::Vec;
+
+// domain tags, so a leaf, an inner node and a root never hash alike
+const LEAF_TAG: u8 = 0x00;
+const NODE_TAG: u8 = 0x01;
+const ROOT_TAG: u8 = 0x02;
+
+fn blake2(input: &[u8]) -> [u8; 32] {
+    let mut output = <Blake2x256 as HashOutput>::Type::default();
+    ink_env::hash_bytes::<Blake2x256>(input, &mut output);
+    output
+}
+
+/// Hash of a leaf: blake2x256(0x00 ++ leaf).
+fn hash_leaf(leaf: &[u8; 32]) -> [u8; 32] {
+    let mut input = [0u8; 33];
+    input[0] = LEAF_TAG;
+    input[1..].copy_from_slice(leaf);
+    blake2(&input)
+}
+
+/// Hash of two child nodes: blake2x256(0x01 ++ left ++ right).
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut input = [0u8; 65];
+    input[0] = NODE_TAG;
+    input[1..33].copy_from_slice(left);
+    input[33..].copy_from_slice(right);
+    blake2(&input)
+}
+
+/// Bind leaf count into the root: blake2x256(0x02 ++ total as u64 le ++ top node).
+fn hash_root(total: usize, node: &[u8; 32]) -> [u8; 32] {
+    let mut input = [0u8; 41];
+    input[0] = ROOT_TAG;
+    input[1..9].copy_from_slice(&(total as u64).to_le_bytes());
+    input[9..].copy_from_slice(node);
+    blake2(&input)
+}
+
+/// Hash one level of the tree, an odd last node is promoted unchanged.
+fn next_level(nodes: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    nodes.chunks(2).map(|pair| {
+        if pair.len() == 2 {
+            hash_pair(&pair[0], &pair[1])
+        } else {
+            pair[0]
+        }
+    }).collect()
+}
+
+/// Merkle root of the leaves, bound to the number of leaves.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    assert!(!leaves.is_empty(), "empty leaves");
+    let mut nodes: Vec<[u8; 32]> = leaves.iter().map(hash_leaf).collect();
+    while nodes.len() > 1 {
+        nodes = next_level(&nodes);
+    }
+    hash_root(leaves.len(), &nodes[0])
+}
+
+/// Sibling hashes from the leaf at `index` up to the top node.
+pub fn merkle_proof(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    assert!(index < leaves.len(), "leaf not found");
+    let mut proof = Vec::new();
+    let mut nodes: Vec<[u8; 32]> = leaves.iter().map(hash_leaf).collect();
+    let mut index = index;
+    while nodes.len() > 1 {
+        let sibling = index ^ 1;
+        if sibling < nodes.len() {
+            proof.push(nodes[sibling]);
+        }
+        nodes = next_level(&nodes);
+        index /= 2;
+    }
+    proof
+}
+
+/// Check the proof of the leaf at `index` of a tree with `total` leaves.
+pub fn verify_merkle_proof(leaf: [u8; 32], index: usize, total: usize, proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+    if index >= total {
+        return false;
+    }
+    let mut node = hash_leaf(&leaf);
+    let mut index = index;
+    let mut width = total;
+    let mut siblings = proof.iter();
+    while width > 1 {
+        let sibling = index ^ 1;
+        if sibling < width {
+            let hash = match siblings.next() {
+                Some(hash) => hash,
+                None => return false,
+            };
+            node = if index & 1 == 0 { hash_pair(&node, hash) } else { hash_pair(hash, &node) };
+        }
+        index /= 2;
+        width = width.div_ceil(2);
+    }
+    siblings.next().is_none() && hash_root(total, &node) == root
+}